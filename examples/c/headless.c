@@ -0,0 +1,88 @@
+/* runs a ROM through the C API for a few seconds holding a key, prints the screen, and checks
+ * that a save state restores it. build the library with
+ *
+ *   cargo rustc --lib --release --no-default-features --features capi --crate-type staticlib
+ *   cc -Iinclude examples/c/headless.c target/release/libchip8.a -lpthread -ldl -lm -o headless
+ *   ./headless games/PONG
+ */
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "chip8.h"
+
+#define FRAMES 120
+
+static uint8_t *read_file(const char *path, size_t *len) {
+    FILE *f = fopen(path, "rb");
+    if (!f) {
+        return NULL;
+    }
+    fseek(f, 0, SEEK_END);
+    long size = ftell(f);
+    fseek(f, 0, SEEK_SET);
+    uint8_t *data = malloc(size > 0 ? size : 1);
+    *len = fread(data, 1, size, f);
+    fclose(f);
+    return data;
+}
+
+static void print_screen(const bool *pixels) {
+    for (int y = 0; y < CHIP8_SCREEN_HEIGHT; y++) {
+        for (int x = 0; x < CHIP8_SCREEN_WIDTH; x++) {
+            putchar(pixels[y * CHIP8_SCREEN_WIDTH + x] ? '#' : '.');
+        }
+        putchar('\n');
+    }
+}
+
+int main(int argc, char **argv) {
+    if (argc != 2) {
+        fprintf(stderr, "usage: %s ROM\n", argv[0]);
+        return 2;
+    }
+    size_t rom_len;
+    uint8_t *rom = read_file(argv[1], &rom_len);
+    if (!rom) {
+        fprintf(stderr, "can't read %s\n", argv[1]);
+        return 1;
+    }
+
+    Chip8 *chip8 = chip8_new();
+    Chip8Status status = chip8_load_rom(chip8, rom, rom_len);
+    free(rom);
+    if (status != CHIP8_STATUS_OK) {
+        fprintf(stderr, "can't load %s: error %d\n", argv[1], status);
+        chip8_free(chip8);
+        return 1;
+    }
+
+    /* hold 1, the left paddle goes up in PONG */
+    chip8_set_key(chip8, 0x1, true);
+    int beeping = 0;
+    for (int frame = 0; frame < FRAMES; frame++) {
+        chip8_run_frame(chip8);
+        beeping += chip8_sound_active(chip8);
+    }
+    const bool *pixels = chip8_get_framebuffer(chip8);
+    print_screen(pixels);
+    printf("%d frames, the buzzer sounded on %d\n", FRAMES, beeping);
+
+    size_t state_len = chip8_save_state(chip8, NULL, 0);
+    uint8_t *state = malloc(state_len);
+    chip8_save_state(chip8, state, state_len);
+    bool before[CHIP8_SCREEN_WIDTH * CHIP8_SCREEN_HEIGHT];
+    memcpy(before, pixels, sizeof(before));
+    for (int frame = 0; frame < 30; frame++) {
+        chip8_run_frame(chip8);
+    }
+    status = chip8_load_state(chip8, state, state_len);
+    int restored = status == CHIP8_STATUS_OK && memcmp(before, pixels, sizeof(before)) == 0;
+    printf("save state of %zu bytes %s\n", state_len, restored ? "restored" : "did NOT restore");
+    free(state);
+
+    int rejected = chip8_set_key(chip8, 16, true) == CHIP8_STATUS_INVALID_ARGUMENT &&
+                   chip8_load_state(chip8, (const uint8_t *)"nope", 4) == CHIP8_STATUS_INVALID_STATE;
+    chip8_free(chip8);
+    return restored && rejected ? 0 : 1;
+}
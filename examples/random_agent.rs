@@ -0,0 +1,47 @@
+// plays a few episodes with random actions, as a starting point for real agents and to check a
+// spec gives rewards and ends episodes
+//
+// cargo run --example random_agent --no-default-features -- games/BRIX specs/BRIX.spec
+use chip8::gym::{Environment, Spec};
+use rand::Rng;
+
+const EPISODES: usize = 5;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 3 {
+        eprintln!("usage: {} ROM SPEC", args[0]);
+        std::process::exit(2);
+    }
+    let rom = std::fs::read(&args[1]).expect("ROM not found");
+    let spec = Spec::load(&args[2]).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let mut env = Environment::new(&rom, spec).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    env.seed(1);
+    println!(
+        "{} actions, observations are {:?}",
+        env.action_space(),
+        env.observation_shape()
+    );
+
+    let mut rng = rand::thread_rng();
+    for episode in 0..EPISODES {
+        env.reset();
+        let (mut total, mut steps) = (0.0, 0);
+        loop {
+            let action = rng.gen_range(0..env.action_space());
+            let (_, reward, done) = env.step(action).unwrap();
+            total += reward;
+            steps += 1;
+            if done {
+                break;
+            }
+        }
+        println!("episode {}: {} steps, return {}", episode, steps, total);
+    }
+}
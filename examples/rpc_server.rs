@@ -0,0 +1,54 @@
+// a headless interpreter driven only through the control server, no window, audio or SDL. useful
+// for automation on machines without a display
+//
+// cargo run --example rpc_server --no-default-features -- 127.0.0.1:6502 [ROM]
+// echo '{"jsonrpc":"2.0","method":"get_registers","id":1}' | nc -q1 127.0.0.1 6502
+use std::time::{Duration, Instant};
+
+use chip8::server::Server;
+use chip8::Chip8;
+
+const FRAME_DURATION: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 2 || args.len() > 3 {
+        eprintln!("usage: {} HOST:PORT|unix:PATH [ROM]", args[0]);
+        std::process::exit(2);
+    }
+    let mut server = Server::bind(&args[1]).unwrap_or_else(|e| {
+        eprintln!("can't listen on {}: {}", args[1], e);
+        std::process::exit(1);
+    });
+    println!("control server listening on {}", server.address());
+
+    let mut chip8 = Chip8::new();
+    // without a ROM, wait paused for a load_rom
+    let mut paused = true;
+    if let Some(path) = args.get(2) {
+        let rom = std::fs::read(path).expect("ROM not found");
+        chip8.load_rom(&rom).expect("can't load the ROM");
+        paused = false;
+    }
+
+    let mut next_frame = Instant::now();
+    loop {
+        server.poll(&mut chip8, &mut paused);
+        if !paused && !chip8.is_halted() {
+            if let Err(e) = chip8.run_frame() {
+                eprintln!("{}", e);
+                paused = !chip8.is_halted();
+            }
+            server.end_frame(&chip8);
+            chip8.clear_drawflag();
+        }
+
+        next_frame += FRAME_DURATION;
+        let now = Instant::now();
+        if next_frame > now {
+            std::thread::sleep(next_frame - now);
+        } else {
+            next_frame = now;
+        }
+    }
+}
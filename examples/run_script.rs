@@ -0,0 +1,45 @@
+// runs a script against a ROM without a window until it calls quit(), for test scenarios and bots
+// on machines without a display. the script's text overlay is printed at the end
+//
+// cargo run --example run_script --no-default-features --features scripting -- games/BRIX scripts/brix_bot.rhai
+use chip8::script::Script;
+use chip8::Chip8;
+
+// scripts that never quit are stopped after ten minutes of game time
+const MAX_FRAMES: u32 = 60 * 60 * 10;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 3 {
+        eprintln!("usage: {} ROM SCRIPT", args[0]);
+        std::process::exit(2);
+    }
+    let rom = std::fs::read(&args[1]).expect("ROM not found");
+    let mut chip8 = Chip8::new();
+    if let Err(e) = chip8.load_rom(&rom) {
+        panic!("{}", e);
+    }
+    let mut script = Script::load(&args[2]).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+
+    for _ in 0..MAX_FRAMES {
+        for key in 0..16 {
+            chip8.set_key(key, script.is_key_pressed(key));
+        }
+        if let Err(e) = script.run_frame(&mut chip8) {
+            eprintln!("{}", e);
+        }
+        if let Some(e) = script.error() {
+            eprintln!("script error: {}", e);
+            std::process::exit(1);
+        }
+        if script.quit_requested() {
+            break;
+        }
+    }
+    for line in script.overlay() {
+        println!("{}", line);
+    }
+}
@@ -0,0 +1,130 @@
+#ifndef CHIP8_H
+#define CHIP8_H
+
+/* generated from src/capi.rs by cbindgen, don't edit */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#define CHIP8_SCREEN_WIDTH 64
+
+#define CHIP8_SCREEN_HEIGHT 32
+
+/**
+ * Result of the calls that can fail. The values are part of the API and never change.
+ */
+typedef enum Chip8Status {
+  CHIP8_STATUS_OK = 0,
+  CHIP8_STATUS_INVALID_ARGUMENT = 1,
+  CHIP8_STATUS_ROM_TOO_LARGE = 2,
+  CHIP8_STATUS_UNKNOWN_OPCODE = 3,
+  CHIP8_STATUS_MACHINE_CODE = 4,
+  CHIP8_STATUS_PC_OUT_OF_BOUNDS = 5,
+  CHIP8_STATUS_STACK_OVERFLOW = 6,
+  CHIP8_STATUS_STACK_UNDERFLOW = 7,
+  CHIP8_STATUS_INVALID_STATE = 8,
+  /**
+   * The interpreter hit a bug. The handle should only be freed afterwards.
+   */
+  CHIP8_STATUS_PANICKED = 9,
+} Chip8Status;
+
+/**
+ * An interpreter instance, create with chip8_new and destroy with chip8_free.
+ */
+typedef struct Chip8 Chip8;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Create an interpreter with nothing loaded. Free it with chip8_free.
+ */
+struct Chip8 *chip8_new(void);
+
+/**
+ * Destroy an interpreter. Null is ignored.
+ *
+ * # Safety
+ * `chip8` must come from chip8_new and not be used afterwards.
+ */
+void chip8_free(struct Chip8 *chip8);
+
+/**
+ * Load `len` bytes of program to 0x200 and reset the machine.
+ *
+ * # Safety
+ * `chip8` must come from chip8_new and `rom` must point to `len` readable bytes.
+ */
+enum Chip8Status chip8_load_rom(struct Chip8 *chip8, const uint8_t *rom, size_t len);
+
+/**
+ * Restart the loaded program.
+ *
+ * # Safety
+ * `chip8` must come from chip8_new.
+ */
+void chip8_reset(struct Chip8 *chip8);
+
+/**
+ * Run one 60Hz frame: 10 instructions and a timer tick. Invalid instructions and stack faults
+ * are skipped like the interpreter does by default.
+ *
+ * # Safety
+ * `chip8` must come from chip8_new.
+ */
+enum Chip8Status chip8_run_frame(struct Chip8 *chip8);
+
+/**
+ * The screen, CHIP8_SCREEN_WIDTH * CHIP8_SCREEN_HEIGHT bools row by row, true for lit pixels.
+ * The pointer stays valid until the handle is freed and always shows the current screen.
+ *
+ * # Safety
+ * `chip8` must come from chip8_new.
+ */
+const bool *chip8_get_framebuffer(const struct Chip8 *chip8);
+
+/**
+ * Press or release a key of the hex keypad, 0 to 15.
+ *
+ * # Safety
+ * `chip8` must come from chip8_new.
+ */
+enum Chip8Status chip8_set_key(struct Chip8 *chip8, uint8_t key, bool pressed);
+
+/**
+ * Whether the buzzer sounded on the last frame.
+ *
+ * # Safety
+ * `chip8` must come from chip8_new.
+ */
+bool chip8_sound_active(const struct Chip8 *chip8);
+
+/**
+ * Write a save state to `out` if it fits in `len` bytes. Returns the size of the state either
+ * way, so calling with a null `out` and 0 tells how much room to make. 0 for a null handle or
+ * a panic.
+ *
+ * # Safety
+ * `chip8` must come from chip8_new and `out` must point to `len` writable bytes.
+ */
+size_t chip8_save_state(const struct Chip8 *chip8, uint8_t *out, size_t len);
+
+/**
+ * Restore a state written by chip8_save_state. A state that doesn't check out leaves the
+ * machine as it was and returns CHIP8_STATUS_INVALID_STATE.
+ *
+ * # Safety
+ * `chip8` must come from chip8_new and `state` must point to `len` readable bytes.
+ */
+enum Chip8Status chip8_load_state(struct Chip8 *chip8, const uint8_t *state, size_t len);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* CHIP8_H */
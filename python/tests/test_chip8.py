@@ -0,0 +1,92 @@
+# in-process tests of the python module. build and install it into the current environment with
+# `maturin develop`, then run `pytest python/tests`
+import os
+
+import pytest
+
+import chip8
+
+GAMES = os.path.join(os.path.dirname(__file__), "..", "..", "games")
+
+
+def rom(name):
+    with open(os.path.join(GAMES, name), "rb") as f:
+        return f.read()
+
+
+@pytest.fixture
+def pong():
+    machine = chip8.Chip8()
+    machine.load_rom(rom("PONG"))
+    return machine
+
+
+def test_starts_at_0x200(pong):
+    assert pong.pc == 0x200
+    assert pong.read_memory(0x200, 2) == rom("PONG")[:2]
+
+
+def test_step_runs_one_instruction(pong):
+    # 6A02, LD VA, 02
+    assert pong.step() == (0x200, 0x6A02)
+    assert pong.v[0xA] == 2
+    assert pong.pc == 0x202
+
+
+def test_framebuffer_shape(pong):
+    pong.run_frame()
+    frame = pong.framebuffer
+    assert frame.shape == (chip8.Chip8.HEIGHT, chip8.Chip8.WIDTH)
+    assert frame.format == "B"
+    assert frame.readonly
+    # the paddles are drawn in the first frame
+    assert sum(frame.tobytes()) > 0
+    assert set(frame.tobytes()) <= {0, 1}
+
+
+def test_framebuffer_with_numpy(pong):
+    np = pytest.importorskip("numpy")
+    pong.run_frame()
+    pixels = np.asarray(pong.framebuffer)
+    assert pixels.shape == (32, 64)
+    assert pixels.dtype == np.uint8
+    # the left paddle is at x=2
+    assert pixels[:, 2].any()
+
+
+def test_registers_and_memory(pong):
+    pong.v = list(range(16))
+    assert pong.v == bytes(range(16))
+    pong.i = 0x300
+    pong.dt = 30
+    assert (pong.i, pong.dt) == (0x300, 30)
+    pong.write_memory(0x300, b"\x01\x02\x03")
+    assert pong.memory[0x300:0x303].tobytes() == b"\x01\x02\x03"
+    with pytest.raises(ValueError):
+        pong.write_memory(0xFFF, b"\x00\x00")
+    with pytest.raises(ValueError):
+        pong.v = [0] * 3
+
+
+def test_keys(pong):
+    pong.set_key(0x1, True)
+    with pytest.raises(ValueError):
+        pong.set_key(16, True)
+
+
+def test_save_state_round_trip(pong):
+    for _ in range(30):
+        pong.run_frame()
+    state = pong.save_state()
+    registers = (pong.pc, pong.i, pong.v)
+    for _ in range(30):
+        pong.run_frame()
+    pong.load_state(state)
+    assert (pong.pc, pong.i, pong.v) == registers
+    with pytest.raises(ValueError):
+        pong.load_state(b"nope")
+
+
+def test_rom_too_large():
+    with pytest.raises(chip8.Chip8Error):
+        chip8.Chip8().load_rom(bytes(4096))
@@ -0,0 +1,13 @@
+# Brix (Andreas Gustafsson), the paddle moves with 4 and 6
+rom = aaa44d0b
+actions = none, 4, 6
+# a point per brick, V5 written by FX33 as three BCD digits
+reward = bcd[0x314]
+# 5 lives in VE, losing one costs 5 points
+reward = 5 * v[e]
+done = v[e] == 0
+# all 96 bricks gone
+done = v[5] == 96
+# the game waits 64 frames before serving the first ball
+start_frames = 70
+frame_skip = 4
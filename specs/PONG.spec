@@ -0,0 +1,10 @@
+# Pong (Paul Vervalin), the agent plays the left paddle on 1 and 4
+rom = 7d75a857
+actions = none, 1, 4
+# VE holds 10 * left score + right score, FX33 writes its digits to 0x2F2
+reward = mem[0x2f3]
+reward = -1 * mem[0x2f4]
+# first to 9, before the digits wrap
+done = mem[0x2f3] == 9
+done = mem[0x2f4] == 9
+frame_skip = 4
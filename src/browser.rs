@@ -0,0 +1,135 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::drivers::MenuInput;
+use crate::romdb;
+use crate::Media;
+
+pub const DEFAULT_ROM_DIR: &str = "games";
+
+// how many recently played ROMs are remembered
+const RECENT_LEN: usize = 10;
+
+struct Entry {
+    path: String,
+    label: String,
+}
+
+// in-window menu listing the ROMs of a directory, most recently played first
+pub struct RomBrowser {
+    dir: String,
+    // where the recent list is kept between runs, None if there is no home directory
+    recent_file: Option<PathBuf>,
+    recent: Vec<String>,
+}
+
+impl RomBrowser {
+    pub fn new(dir: &str) -> Self {
+        let recent_file = env::var_os("HOME").map(|home| Path::new(&home).join(".chip8_recent"));
+        let recent = recent_file
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|text| text.lines().map(str::to_string).collect())
+            .unwrap_or_default();
+
+        Self {
+            dir: dir.to_string(),
+            recent_file,
+            recent,
+        }
+    }
+
+    pub fn add_recent(&mut self, path: &str) {
+        self.recent.retain(|recent| recent != path);
+        self.recent.insert(0, path.to_string());
+        self.recent.truncate(RECENT_LEN);
+        if let Some(file) = &self.recent_file {
+            let _ = fs::write(file, self.recent.join("\n"));
+        }
+    }
+
+    fn label(path: &str) -> String {
+        let name = Path::new(path)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.to_string());
+        match fs::read(path).ok().and_then(|rom| romdb::title_for(&rom)) {
+            Some(title) => title.to_string(),
+            None => name,
+        }
+    }
+
+    // recently played ROMs that still exist, then everything else in the directory by name
+    fn entries(&self) -> Vec<Entry> {
+        let mut paths: Vec<String> = self
+            .recent
+            .iter()
+            .filter(|path| Path::new(path).is_file())
+            .cloned()
+            .collect();
+
+        let mut others: Vec<String> = fs::read_dir(&self.dir)
+            .map(|dir| {
+                dir.filter_map(|entry| entry.ok())
+                    .map(|entry| entry.path())
+                    .filter(|path| path.is_file())
+                    .map(|path| path.to_string_lossy().into_owned())
+                    .filter(|path| !paths.contains(path))
+                    .collect()
+            })
+            .unwrap_or_default();
+        others.sort();
+        paths.extend(others);
+
+        paths
+            .into_iter()
+            .map(|path| Entry {
+                label: Self::label(&path),
+                path,
+            })
+            .collect()
+    }
+
+    // show the menu until a ROM is picked. returns None if the user quits instead
+    pub fn choose(&mut self, media: &mut Media) -> Option<String> {
+        let entries = self.entries();
+        let labels: Vec<String> = entries.iter().map(|entry| entry.label.clone()).collect();
+        let title = if entries.is_empty() {
+            format!("No ROMs in {}", self.dir)
+        } else {
+            "Select a ROM".to_string()
+        };
+
+        media.display.set_title("chip8");
+        let mut selected = 0;
+        let mut redraw = true;
+        let mut messages = Vec::new();
+        loop {
+            // messages like a ROM that failed to load come and go on their own
+            let showing = media.display.osd.messages();
+            if redraw || showing != messages {
+                media.display.draw_menu(&title, &labels, selected);
+                messages = showing;
+                redraw = false;
+            }
+            for input in media.keypad.poll_menu() {
+                match input {
+                    MenuInput::Up => selected = selected.saturating_sub(1),
+                    MenuInput::Down => {
+                        selected = (selected + 1).min(entries.len().saturating_sub(1))
+                    }
+                    MenuInput::Select => {
+                        if let Some(entry) = entries.get(selected) {
+                            return Some(entry.path.clone());
+                        }
+                    }
+                    MenuInput::Quit => return None,
+                }
+                redraw = true;
+            }
+            std::thread::sleep(Duration::from_millis(16));
+        }
+    }
+}
@@ -0,0 +1,218 @@
+// C API for embedding the interpreter in C and C++ programs. include/chip8.h is generated from
+// this file by cbindgen when building with the capi feature. link with target/release/libchip8.a
+// (plus -lpthread -ldl -lm) or the shared libchip8.so
+//
+// every function takes the handle from chip8_new, a null handle is rejected with
+// CHIP8_STATUS_INVALID_ARGUMENT or ignored. nothing here is thread safe, use a handle from one thread at
+// a time. a panic never unwinds into C, it is caught and reported as CHIP8_STATUS_PANICKED or the
+// function's empty result
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+use crate::Chip8Error;
+
+// spelled out for cbindgen, which can't follow the paths
+pub const CHIP8_SCREEN_WIDTH: usize = 64;
+pub const CHIP8_SCREEN_HEIGHT: usize = 32;
+const _: () = assert!(
+    CHIP8_SCREEN_WIDTH == crate::SCREEN_WIDTH && CHIP8_SCREEN_HEIGHT == crate::SCREEN_HEIGHT
+);
+
+/// An interpreter instance, create with chip8_new and destroy with chip8_free.
+pub struct Chip8 {
+    chip8: crate::Chip8,
+}
+
+/// Result of the calls that can fail. The values are part of the API and never change.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Chip8Status {
+    Ok = 0,
+    InvalidArgument = 1,
+    RomTooLarge = 2,
+    UnknownOpcode = 3,
+    MachineCode = 4,
+    PcOutOfBounds = 5,
+    StackOverflow = 6,
+    StackUnderflow = 7,
+    InvalidState = 8,
+    /// The interpreter hit a bug. The handle should only be freed afterwards.
+    Panicked = 9,
+}
+
+impl From<Chip8Error> for Chip8Status {
+    fn from(error: Chip8Error) -> Self {
+        match error {
+            Chip8Error::UnknownOpcode { .. } => Chip8Status::UnknownOpcode,
+            Chip8Error::MachineCode { .. } => Chip8Status::MachineCode,
+            Chip8Error::PcOutOfBounds { .. } => Chip8Status::PcOutOfBounds,
+            Chip8Error::StackOverflow { .. } => Chip8Status::StackOverflow,
+            Chip8Error::StackUnderflow { .. } => Chip8Status::StackUnderflow,
+            Chip8Error::RomTooLarge { .. } => Chip8Status::RomTooLarge,
+        }
+    }
+}
+
+fn status(result: Result<(), Chip8Error>) -> Chip8Status {
+    result.map_or_else(Chip8Status::from, |()| Chip8Status::Ok)
+}
+
+// run an API function body, returning `panicked` if it panics. the handle may be left half
+// updated, which is why callers are told to only free it then
+fn guard<T>(panicked: T, body: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or(panicked)
+}
+
+/// Create an interpreter with nothing loaded. Free it with chip8_free.
+#[no_mangle]
+pub extern "C" fn chip8_new() -> *mut Chip8 {
+    guard(ptr::null_mut(), || {
+        Box::into_raw(Box::new(Chip8 {
+            chip8: crate::Chip8::new(),
+        }))
+    })
+}
+
+/// Destroy an interpreter. Null is ignored.
+///
+/// # Safety
+/// `chip8` must come from chip8_new and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_free(chip8: *mut Chip8) {
+    if !chip8.is_null() {
+        guard((), || drop(Box::from_raw(chip8)));
+    }
+}
+
+/// Load `len` bytes of program to 0x200 and reset the machine.
+///
+/// # Safety
+/// `chip8` must come from chip8_new and `rom` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_load_rom(
+    chip8: *mut Chip8,
+    rom: *const u8,
+    len: usize,
+) -> Chip8Status {
+    let Some(chip8) = chip8.as_mut() else {
+        return Chip8Status::InvalidArgument;
+    };
+    if rom.is_null() && len > 0 {
+        return Chip8Status::InvalidArgument;
+    }
+    let rom = if len == 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(rom, len)
+    };
+    guard(Chip8Status::Panicked, || status(chip8.chip8.load_rom(rom)))
+}
+
+/// Restart the loaded program.
+///
+/// # Safety
+/// `chip8` must come from chip8_new.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_reset(chip8: *mut Chip8) {
+    if let Some(chip8) = chip8.as_mut() {
+        guard((), || chip8.chip8.reset());
+    }
+}
+
+/// Run one 60Hz frame: 10 instructions and a timer tick. Invalid instructions and stack faults
+/// are skipped like the interpreter does by default.
+///
+/// # Safety
+/// `chip8` must come from chip8_new.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_run_frame(chip8: *mut Chip8) -> Chip8Status {
+    match chip8.as_mut() {
+        Some(chip8) => guard(Chip8Status::Panicked, || status(chip8.chip8.run_frame())),
+        None => Chip8Status::InvalidArgument,
+    }
+}
+
+/// The screen, CHIP8_SCREEN_WIDTH * CHIP8_SCREEN_HEIGHT bools row by row, true for lit pixels.
+/// The pointer stays valid until the handle is freed and always shows the current screen.
+///
+/// # Safety
+/// `chip8` must come from chip8_new.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_get_framebuffer(chip8: *const Chip8) -> *const bool {
+    match chip8.as_ref() {
+        Some(chip8) => chip8.chip8.framebuffer().as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+/// Press or release a key of the hex keypad, 0 to 15.
+///
+/// # Safety
+/// `chip8` must come from chip8_new.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_set_key(chip8: *mut Chip8, key: u8, pressed: bool) -> Chip8Status {
+    match chip8.as_mut() {
+        Some(chip8) if key < 16 => guard(Chip8Status::Panicked, || {
+            chip8.chip8.set_key(key as usize, pressed);
+            Chip8Status::Ok
+        }),
+        _ => Chip8Status::InvalidArgument,
+    }
+}
+
+/// Whether the buzzer sounded on the last frame.
+///
+/// # Safety
+/// `chip8` must come from chip8_new.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_sound_active(chip8: *const Chip8) -> bool {
+    chip8
+        .as_ref()
+        .is_some_and(|chip8| guard(false, || chip8.chip8.sound_active()))
+}
+
+/// Write a save state to `out` if it fits in `len` bytes. Returns the size of the state either
+/// way, so calling with a null `out` and 0 tells how much room to make. 0 for a null handle or
+/// a panic.
+///
+/// # Safety
+/// `chip8` must come from chip8_new and `out` must point to `len` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_save_state(chip8: *const Chip8, out: *mut u8, len: usize) -> usize {
+    let Some(chip8) = chip8.as_ref() else {
+        return 0;
+    };
+    guard(0, || {
+        let state = chip8.chip8.save_state();
+        if !out.is_null() && len >= state.len() {
+            ptr::copy_nonoverlapping(state.as_ptr(), out, state.len());
+        }
+        state.len()
+    })
+}
+
+/// Restore a state written by chip8_save_state. A state that doesn't check out leaves the
+/// machine as it was and returns CHIP8_STATUS_INVALID_STATE.
+///
+/// # Safety
+/// `chip8` must come from chip8_new and `state` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_load_state(
+    chip8: *mut Chip8,
+    state: *const u8,
+    len: usize,
+) -> Chip8Status {
+    let Some(chip8) = chip8.as_mut() else {
+        return Chip8Status::InvalidArgument;
+    };
+    if state.is_null() {
+        return Chip8Status::InvalidArgument;
+    }
+    let state = std::slice::from_raw_parts(state, len);
+    guard(Chip8Status::Panicked, || {
+        match chip8.chip8.load_state(state) {
+            Ok(()) => Chip8Status::Ok,
+            Err(_) => Chip8Status::InvalidState,
+        }
+    })
+}
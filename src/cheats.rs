@@ -0,0 +1,186 @@
+// cheat codes, written to memory and the V registers between frames. a cheat file has a section
+// per ROM, named by its rom_hash, and a cheat per line: a name, freeze or poke and the codes to
+// write, ADDRESS=VALUE for a byte of memory or vX=VALUE for a register:
+//
+//  [ead625b8]
+//  # Space Invaders is over when the invaders reach row 0x18 in VC
+//  infinite lives = freeze vc=4
+//  # frames between invader moves, it drops by 12 every wave
+//  slow invaders = poke vd=60
+//
+//  [aaa44d0b]
+//  infinite lives = freeze ve=5
+//
+// freeze cheats are switched on when loaded and rewrite their codes before every frame, so the
+// game can't change them. poke cheats write their codes once, whenever they are picked from the
+// cheat menu. numbers are decimal, or hex with 0x
+use std::collections::HashMap;
+use std::fs;
+
+use crate::Chip8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Target {
+    Memory(u16),
+    Register(usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Code {
+    pub target: Target,
+    pub value: u8,
+}
+
+impl Code {
+    fn parse(text: &str) -> Option<Self> {
+        let (target, value) = text.split_once('=')?;
+        let target = target.trim();
+        let target = match target
+            .strip_prefix('v')
+            .or_else(|| target.strip_prefix('V'))
+        {
+            Some(x) => Target::Register(usize::from_str_radix(x, 16).ok().filter(|x| *x < 16)?),
+            None => Target::Memory(parse_number(target).filter(|a| *a < 0x1000)? as u16),
+        };
+        let value = parse_number(value.trim()).filter(|v| *v <= 0xff)? as u8;
+        Some(Self { target, value })
+    }
+
+    pub fn write(&self, chip8: &mut Chip8) {
+        match self.target {
+            Target::Memory(address) => chip8.memory_mut()[address as usize] = self.value,
+            Target::Register(x) => chip8.set_v(x, self.value),
+        }
+    }
+}
+
+impl std::fmt::Display for Code {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.target {
+            Target::Memory(address) => write!(f, "0x{:03x}={}", address, self.value),
+            Target::Register(x) => write!(f, "v{:x}={}", x, self.value),
+        }
+    }
+}
+
+// decimal, or hex with 0x
+fn parse_number(text: &str) -> Option<u64> {
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    // written once when picked from the menu
+    Poke,
+    // written before every frame while switched on
+    Freeze,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cheat {
+    pub name: String,
+    pub mode: Mode,
+    pub codes: Vec<Code>,
+    // only used by freeze cheats
+    pub enabled: bool,
+}
+
+impl Cheat {
+    fn parse(text: &str) -> Option<Self> {
+        let (name, rest) = text.split_once('=')?;
+        let (mode, codes) = rest.trim().split_once(char::is_whitespace)?;
+        let mode = match mode {
+            "poke" => Mode::Poke,
+            "freeze" => Mode::Freeze,
+            _ => return None,
+        };
+        Some(Self {
+            name: name.trim().to_string(),
+            mode,
+            codes: codes.split(',').map(Code::parse).collect::<Option<_>>()?,
+            enabled: mode == Mode::Freeze,
+        })
+    }
+
+    pub fn write(&self, chip8: &mut Chip8) {
+        for code in &self.codes {
+            code.write(chip8);
+        }
+    }
+}
+
+// a line of a cheat file
+impl std::fmt::Display for Cheat {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mode = match self.mode {
+            Mode::Poke => "poke",
+            Mode::Freeze => "freeze",
+        };
+        let codes: Vec<String> = self.codes.iter().map(Code::to_string).collect();
+        write!(f, "{} = {} {}", self.name, mode, codes.join(", "))
+    }
+}
+
+// the cheats of every ROM in a cheat file. switching them on and off sticks for the ROM until the
+// program exits
+#[derive(Clone, Debug, Default)]
+pub struct CheatFile {
+    roms: HashMap<u32, Vec<Cheat>>,
+}
+
+impl CheatFile {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text =
+            fs::read_to_string(path).map_err(|e| format!("can't read cheats {}: {}", path, e))?;
+        Self::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut file = Self::default();
+        let mut hash = None;
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if line.starts_with('[') && line.ends_with(']') {
+                let name = line[1..line.len() - 1].trim().trim_start_matches("0x");
+                hash = Some(
+                    u32::from_str_radix(name, 16)
+                        .map_err(|_| format!("line {}: bad ROM hash {}", n + 1, name))?,
+                );
+                continue;
+            }
+            let hash = hash.ok_or(format!("line {}: cheat outside of a section", n + 1))?;
+            let cheat = Cheat::parse(line).ok_or(format!(
+                "line {}: expected `name = freeze|poke ADDRESS=VALUE, ...`",
+                n + 1
+            ))?;
+            file.roms.entry(hash).or_default().push(cheat);
+        }
+        Ok(file)
+    }
+
+    // the cheats for a ROM, see romdb::rom_hash
+    pub fn cheats(&self, rom_hash: u32) -> &[Cheat] {
+        self.roms.get(&rom_hash).map_or(&[], |cheats| cheats)
+    }
+
+    pub fn cheats_mut(&mut self, rom_hash: u32) -> &mut [Cheat] {
+        self.roms
+            .get_mut(&rom_hash)
+            .map_or(&mut [], |cheats| cheats)
+    }
+
+    // write the freeze cheats that are switched on, call before every frame
+    pub fn apply(&self, rom_hash: u32, chip8: &mut Chip8) {
+        for cheat in self.cheats(rom_hash) {
+            if cheat.mode == Mode::Freeze && cheat.enabled {
+                cheat.write(chip8);
+            }
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use crate::{disasm, png};
+
+// how each byte of memory was used, as bit flags
+pub const OPCODE: u8 = 0b0001;
+// second byte of an executed instruction
+pub const OPERAND: u8 = 0b0010;
+// read as data by DXYN, FX65 or F002
+pub const READ: u8 = 0b0100;
+// written by FX33 or FX55
+pub const WRITTEN: u8 = 0b1000;
+
+// the png draws memory as a 64x64 grid of bytes, each byte a square of this many pixels
+const PNG_COLUMNS: usize = 64;
+const PNG_CELL: usize = 8;
+
+pub struct Coverage {
+    flags: Vec<u8>,
+}
+
+impl Default for Coverage {
+    fn default() -> Self {
+        Self {
+            flags: vec![0; 4096],
+        }
+    }
+}
+
+impl Coverage {
+    fn mark(&mut self, address: usize, len: usize, flag: u8) {
+        for offset in 0..len {
+            self.flags[(address + offset) & 0xfff] |= flag;
+        }
+    }
+
+    pub fn mark_executed(&mut self, address: u16) {
+        self.mark(address as usize, 1, OPCODE);
+        self.mark(address as usize + 1, 1, OPERAND);
+    }
+
+    pub fn mark_read(&mut self, address: u16, len: usize) {
+        self.mark(address as usize, len, READ);
+    }
+
+    pub fn mark_written(&mut self, address: u16, len: usize) {
+        self.mark(address as usize, len, WRITTEN);
+    }
+
+    pub fn flags(&self, address: u16) -> u8 {
+        self.flags[address as usize & 0xfff]
+    }
+
+    // the map of a ROM of the given length loaded at start, one entry per ROM byte
+    pub fn report_json(&self, start: u16, len: usize) -> String {
+        let bytes: Vec<String> = (0..len)
+            .map(|offset| {
+                let flags = self.flags(start + offset as u16);
+                let mut kinds = Vec::new();
+                if flags & OPCODE != 0 {
+                    kinds.push("\"opcode\"");
+                }
+                if flags & OPERAND != 0 {
+                    kinds.push("\"operand\"");
+                }
+                if flags & READ != 0 {
+                    kinds.push("\"read\"");
+                }
+                if flags & WRITTEN != 0 {
+                    kinds.push("\"written\"");
+                }
+                format!(
+                    "{{\"address\":{},\"use\":[{}]}}",
+                    start as usize + offset,
+                    kinds.join(",")
+                )
+            })
+            .collect();
+        format!(
+            "{{\"start\":{},\"length\":{},\"bytes\":[{}]}}\n",
+            start,
+            len,
+            bytes.join(",")
+        )
+    }
+
+    // executed bytes are green, data reads blue and writes red. bytes used in several ways mix
+    // their colours, unused bytes are dark grey
+    fn colour(flags: u8) -> [u8; 3] {
+        if flags == 0 {
+            return [32, 32, 32];
+        }
+        let mut colour = [0, 0, 0];
+        if flags & WRITTEN != 0 {
+            colour[0] = 255;
+        }
+        if flags & (OPCODE | OPERAND) != 0 {
+            colour[1] = if flags & OPCODE != 0 { 255 } else { 160 };
+        }
+        if flags & READ != 0 {
+            colour[2] = 255;
+        }
+        colour
+    }
+
+    pub fn write_png(&self, path: &str) -> std::io::Result<()> {
+        let rows = self.flags.len() / PNG_COLUMNS;
+        let width = PNG_COLUMNS * PNG_CELL;
+        let height = rows * PNG_CELL;
+        let mut rgb = vec![0u8; width * height * 3];
+        for y in 0..height {
+            for x in 0..width {
+                let address = (y / PNG_CELL) * PNG_COLUMNS + x / PNG_CELL;
+                let i = (y * width + x) * 3;
+                rgb[i..i + 3].copy_from_slice(&Self::colour(self.flags[address]));
+            }
+        }
+        png::write_rgb(path, width as u32, height as u32, &rgb)
+    }
+
+    // write <prefix>.json, <prefix>.png and a disassembly of the ROM split by the map to
+    // <prefix>.asm
+    pub fn write_reports(&self, prefix: &str, rom: &[u8]) -> std::io::Result<()> {
+        std::fs::write(
+            format!("{}.json", prefix),
+            self.report_json(0x200, rom.len()),
+        )?;
+        self.write_png(&format!("{}.png", prefix))?;
+        std::fs::write(
+            format!("{}.asm", prefix),
+            disasm::disassemble(rom, Some(self)),
+        )
+    }
+}
@@ -0,0 +1,63 @@
+use std::fmt::Write;
+
+use crate::coverage::{Coverage, OPCODE, READ, WRITTEN};
+use crate::opcodes;
+
+// where ROMs are loaded
+const ROM_START: u16 = 0x200;
+
+// a data byte as sprite pixels, e.g. "#..##..#"
+fn bits(byte: u8) -> String {
+    (0..8)
+        .map(|bit| if byte & (0x80 >> bit) != 0 { '#' } else { '.' })
+        .collect()
+}
+
+fn data_line(out: &mut String, address: u16, byte: u8, flags: u8) {
+    let mut uses = Vec::new();
+    if flags & READ != 0 {
+        uses.push("read");
+    }
+    if flags & WRITTEN != 0 {
+        uses.push("written");
+    }
+    if uses.is_empty() {
+        uses.push("unused");
+    }
+    let _ = writeln!(
+        out,
+        "{:03X}  {:02X}    db   {:02X}          ; {} {}",
+        address,
+        byte,
+        byte,
+        bits(byte),
+        uses.join(", ")
+    );
+}
+
+// disassemble a ROM loaded at 0x200. without a coverage map every pair of bytes is decoded as an
+// instruction. with one, only bytes that were executed as opcodes are decoded and everything
+// else is listed as data, so sprites and code are told apart exactly
+pub fn disassemble(rom: &[u8], coverage: Option<&Coverage>) -> String {
+    let mut out = String::new();
+    let mut offset = 0;
+    while offset < rom.len() {
+        let address = ROM_START + offset as u16;
+        let flags = coverage.map_or(OPCODE, |coverage| coverage.flags(address));
+        if flags & OPCODE != 0 && offset + 1 < rom.len() {
+            let opcode = (rom[offset] as u16) << 8 | rom[offset + 1] as u16;
+            let _ = writeln!(
+                out,
+                "{:03X}  {:04X}  {}",
+                address,
+                opcode,
+                opcodes::mnemonic(opcode)
+            );
+            offset += 2;
+        } else {
+            data_line(&mut out, address, rom[offset], flags);
+            offset += 1;
+        }
+    }
+    out
+}
@@ -1,56 +1,171 @@
-use sdl2;
-use sdl2::audio::{AudioDevice, AudioCallback};
+use sdl2::audio::{AudioCallback, AudioDevice};
 
-pub struct Audio {
-    dev: AudioDevice<SquareWave>
+use super::wav::WavWriter;
+use crate::synth::{Buzzer, SamplePattern, SquareWave, Tone, SAMPLES_PER_FRAME, SAMPLE_RATE};
+
+impl AudioCallback for SquareWave {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [f32]) {
+        self.fill(out);
+    }
 }
 
-struct SquareWave {
-    phase_inc: f32,
-    phase: f32,
-    volume: f32,
+impl AudioCallback for SamplePattern {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [f32]) {
+        self.fill(out);
+    }
 }
 
-impl AudioCallback for SquareWave {
+impl AudioCallback for Buzzer {
     type Channel = f32;
 
     fn callback(&mut self, out: &mut [f32]) {
-        for x in out.iter_mut() {
-            *x = if self.phase <= 0.5 {
-                self.volume
-            } else {
-                -self.volume
-            };
-            self.phase = (self.phase + self.phase_inc) % 1.0;
-        }
+        self.fill(out);
     }
 }
 
+enum Output {
+    Device(AudioDevice<Buzzer>),
+    // no audio device, the buzzer is rendered one frame of samples per timer tick and
+    // optionally written to a WAV file
+    Headless {
+        wave: Buzzer,
+        frame: Vec<f32>,
+        writer: Option<WavWriter>,
+        // whether the sound timer was running, one entry per timer tick
+        activity: Vec<bool>,
+    },
+}
+
+pub struct Audio {
+    output: Output,
+}
+
 impl Audio {
     pub fn new(sdl_context: &sdl2::Sdl) -> Self {
+        Self::with_tone(sdl_context, Tone::default())
+    }
+
+    pub fn with_tone(sdl_context: &sdl2::Sdl, tone: Tone) -> Self {
         let a_sub = sdl_context.audio().unwrap();
         let spec = sdl2::audio::AudioSpecDesired {
-            freq: Some(44100),
+            freq: Some(SAMPLE_RATE),
             channels: Some(1),
             samples: None,
         };
-        let dev = a_sub.open_playback(None, &spec, |spec| {
-            // callback
-            SquareWave {
-                phase_inc: 440.0 / spec.freq as f32,
-                phase: 0.0,
-                volume: 0.25,
+        let dev = a_sub
+            .open_playback(None, &spec, |spec| {
+                // callback
+                Buzzer::new(tone, spec.freq)
+            })
+            .unwrap();
+
+        Self {
+            output: Output::Device(dev),
+        }
+    }
+
+    // render the buzzer without an audio device. with a path, the samples are written to a WAV
+    // file as they are produced
+    pub fn headless(tone: Tone, wav_path: Option<&str>) -> std::io::Result<Self> {
+        let writer = match wav_path {
+            Some(path) => Some(WavWriter::create(path, SAMPLE_RATE as u32)?),
+            None => None,
+        };
+        Ok(Self {
+            output: Output::Headless {
+                wave: Buzzer::new(tone, SAMPLE_RATE),
+                frame: vec![0.0; SAMPLES_PER_FRAME],
+                writer,
+                activity: Vec::new(),
+            },
+        })
+    }
+
+    pub fn set_tone(&mut self, tone: Tone) {
+        match &mut self.output {
+            Output::Device(dev) => dev.lock().set_tone(tone),
+            Output::Headless { wave, .. } => wave.set_tone(tone),
+        }
+    }
+
+    // XO-CHIP F002. the pattern is played instead of the tone until it is set back to None
+    pub fn set_pattern(&mut self, pattern: Option<[u8; 16]>) {
+        match &mut self.output {
+            Output::Device(dev) => dev.lock().set_pattern(pattern),
+            Output::Headless { wave, .. } => wave.set_pattern(pattern),
+        }
+    }
+
+    // XO-CHIP FX3A
+    pub fn set_pitch(&mut self, pitch: u8) {
+        match &mut self.output {
+            Output::Device(dev) => dev.lock().set_pitch(pitch),
+            Output::Headless { wave, .. } => wave.set_pitch(pitch),
+        }
+    }
+
+    // called on every timer tick with whether the sound timer is non-zero. the device is
+    // resumed as soon as the timer starts; once it stops the tone is released and the device
+    // paused on the first tick after the release has faded out
+    pub fn set_active(&mut self, active: bool) {
+        match &mut self.output {
+            Output::Device(dev) => {
+                let silent = {
+                    let mut wave = dev.lock();
+                    wave.set_gate(active);
+                    wave.is_silent()
+                };
+                if active {
+                    dev.resume();
+                } else if silent {
+                    dev.pause();
+                }
+            }
+            Output::Headless {
+                wave,
+                frame,
+                writer,
+                activity,
+            } => {
+                activity.push(active);
+                wave.set_gate(active);
+                wave.fill(frame);
+                if let Some(w) = writer {
+                    if let Err(e) = w.write_samples(frame) {
+                        eprintln!("can't write audio: {}", e);
+                        *writer = None;
+                    }
+                }
             }
-        }).unwrap();
+        }
+    }
 
-        Self { dev }
+    // silence the device without counting a timer tick, e.g. while the emulator is paused
+    pub fn mute(&mut self) {
+        if let Output::Device(dev) = &mut self.output {
+            dev.lock().set_gate(false);
+            dev.pause();
+        }
     }
 
-    pub fn beep(&self) {
-        self.dev.resume();
+    // samples rendered on the last timer tick, empty when playing through a device
+    pub fn last_frame(&self) -> &[f32] {
+        match &self.output {
+            Output::Device(_) => &[],
+            Output::Headless { frame, .. } => frame,
+        }
     }
 
-    pub fn beep_stop(&self) {
-        self.dev.pause();
+    // whether the sound timer was running on each timer tick so far, empty when playing through
+    // a device
+    pub fn activity(&self) -> &[bool] {
+        match &self.output {
+            Output::Device(_) => &[],
+            Output::Headless { activity, .. } => activity,
+        }
     }
 }
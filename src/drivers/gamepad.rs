@@ -0,0 +1,140 @@
+use sdl2::controller::{Axis, Button};
+use sdl2::event::Event;
+use std::collections::{HashMap, HashSet};
+
+// two controllers are enough for every two player game on the chip8
+pub const MAX_PLAYERS: usize = 2;
+
+pub const DEFAULT_DEADZONE: i16 = 8000;
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum PadControl {
+    Button(Button),
+    // an analog axis pushed past the deadzone, true for the positive direction
+    Axis(Axis, bool),
+}
+
+// a control on the controller of a given player
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct PadInput {
+    pub player: usize,
+    pub control: PadControl,
+}
+
+impl PadInput {
+    pub fn button(player: usize, button: Button) -> Self {
+        Self {
+            player,
+            control: PadControl::Button(button),
+        }
+    }
+
+    pub fn axis(player: usize, axis: Axis, positive: bool) -> Self {
+        Self {
+            player,
+            control: PadControl::Axis(axis, positive),
+        }
+    }
+
+    // parse the keymap syntax: pad<player>:<button> or pad<player>:<axis><+|->, using the
+    // names from SDL controller mappings (pad0:dpup, pad1:a, pad0:lefty-)
+    pub fn from_name(name: &str) -> Option<Self> {
+        let (player, control) = name.strip_prefix("pad")?.split_once(':')?;
+        let player = player.parse::<usize>().ok().filter(|p| *p < MAX_PLAYERS)?;
+        if let Some(axis) = control.strip_suffix('+') {
+            return Some(Self::axis(player, Axis::from_string(axis)?, true));
+        }
+        if let Some(axis) = control.strip_suffix('-') {
+            return Some(Self::axis(player, Axis::from_string(axis)?, false));
+        }
+        Some(Self::button(player, Button::from_string(control)?))
+    }
+
+    pub fn name(&self) -> String {
+        match self.control {
+            PadControl::Button(button) => format!("pad{}:{}", self.player, button.string()),
+            PadControl::Axis(axis, positive) => format!(
+                "pad{}:{}{}",
+                self.player,
+                axis.string(),
+                if positive { '+' } else { '-' }
+            ),
+        }
+    }
+}
+
+// state of the connected controllers. controllers are given the lowest free player slot when
+// they are plugged in and keep it until they are unplugged
+#[derive(Default)]
+pub struct Gamepads {
+    // joystick instance id of the controller of each player
+    players: [Option<u32>; MAX_PLAYERS],
+    buttons: HashSet<(usize, Button)>,
+    axes: HashMap<(usize, Axis), i16>,
+}
+
+impl Gamepads {
+    // returns the player slot given to the controller, None if every slot is taken
+    pub fn connect(&mut self, instance_id: u32) -> Option<usize> {
+        if let Some(player) = self.player_of(instance_id) {
+            return Some(player);
+        }
+        let player = self.players.iter().position(Option::is_none)?;
+        self.players[player] = Some(instance_id);
+        Some(player)
+    }
+
+    pub fn disconnect(&mut self, instance_id: u32) {
+        if let Some(player) = self.player_of(instance_id) {
+            self.players[player] = None;
+            self.buttons.retain(|(p, _)| *p != player);
+            self.axes.retain(|(p, _), _| *p != player);
+        }
+    }
+
+    pub fn player_of(&self, instance_id: u32) -> Option<usize> {
+        self.players.iter().position(|id| *id == Some(instance_id))
+    }
+
+    // update button and axis state from a controller event. events from unknown controllers
+    // are dropped
+    pub fn handle_event(&mut self, event: &Event) {
+        match *event {
+            Event::ControllerButtonDown { which, button, .. } => {
+                if let Some(player) = self.player_of(which) {
+                    self.buttons.insert((player, button));
+                }
+            }
+            Event::ControllerButtonUp { which, button, .. } => {
+                if let Some(player) = self.player_of(which) {
+                    self.buttons.remove(&(player, button));
+                }
+            }
+            Event::ControllerAxisMotion {
+                which, axis, value, ..
+            } => {
+                if let Some(player) = self.player_of(which) {
+                    self.axes.insert((player, axis), value);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // every control currently held, axes only count once they leave the deadzone
+    pub fn active_inputs(&self, deadzone: i16) -> Vec<PadInput> {
+        let mut inputs: Vec<PadInput> = self
+            .buttons
+            .iter()
+            .map(|(player, button)| PadInput::button(*player, *button))
+            .collect();
+        for ((player, axis), value) in self.axes.iter() {
+            if *value > deadzone {
+                inputs.push(PadInput::axis(*player, *axis, true));
+            } else if (*value as i32) < -(deadzone as i32) {
+                inputs.push(PadInput::axis(*player, *axis, false));
+            }
+        }
+        inputs
+    }
+}
@@ -0,0 +1,102 @@
+use sdl2::keyboard::Scancode;
+
+// emulator controls, as opposed to chip8 keys
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Hotkey {
+    Pause,
+    Reset,
+    FrameAdvance,
+    SpeedUp,
+    SpeedDown,
+    ToggleCounters,
+    Cheats,
+    Menu,
+    Quit,
+}
+
+// host keys for the emulator controls. turbo is not a Hotkey because it is held rather than
+// pressed
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hotkeys {
+    pub pause: Scancode,
+    pub reset: Scancode,
+    pub frame_advance: Scancode,
+    pub speed_up: Scancode,
+    pub speed_down: Scancode,
+    pub turbo: Scancode,
+    pub counters: Scancode,
+    pub cheats: Scancode,
+    pub menu: Scancode,
+    pub quit: Scancode,
+}
+
+impl Default for Hotkeys {
+    fn default() -> Self {
+        Self {
+            pause: Scancode::P,
+            reset: Scancode::Backspace,
+            frame_advance: Scancode::N,
+            speed_up: Scancode::Equals,
+            speed_down: Scancode::Minus,
+            turbo: Scancode::Tab,
+            counters: Scancode::F1,
+            cheats: Scancode::F3,
+            menu: Scancode::F2,
+            quit: Scancode::Escape,
+        }
+    }
+}
+
+impl Hotkeys {
+    pub fn hotkey_for(&self, scancode: Scancode) -> Option<Hotkey> {
+        let hotkeys = [
+            (self.pause, Hotkey::Pause),
+            (self.reset, Hotkey::Reset),
+            (self.frame_advance, Hotkey::FrameAdvance),
+            (self.speed_up, Hotkey::SpeedUp),
+            (self.speed_down, Hotkey::SpeedDown),
+            (self.counters, Hotkey::ToggleCounters),
+            (self.cheats, Hotkey::Cheats),
+            (self.menu, Hotkey::Menu),
+            (self.quit, Hotkey::Quit),
+        ];
+        hotkeys
+            .iter()
+            .find(|(bound, _)| *bound == scancode)
+            .map(|(_, hotkey)| *hotkey)
+    }
+
+    // set a control by the name used in the [hotkeys] section of the keymap config
+    pub fn set(&mut self, name: &str, scancode: Scancode) -> Option<()> {
+        let field = match name {
+            "pause" => &mut self.pause,
+            "reset" => &mut self.reset,
+            "frame-advance" => &mut self.frame_advance,
+            "speed-up" => &mut self.speed_up,
+            "speed-down" => &mut self.speed_down,
+            "turbo" => &mut self.turbo,
+            "counters" => &mut self.counters,
+            "cheats" => &mut self.cheats,
+            "menu" => &mut self.menu,
+            "quit" => &mut self.quit,
+            _ => return None,
+        };
+        *field = scancode;
+        Some(())
+    }
+
+    pub fn bindings(&self) -> [(&'static str, Scancode); 10] {
+        [
+            ("pause", self.pause),
+            ("reset", self.reset),
+            ("frame-advance", self.frame_advance),
+            ("speed-up", self.speed_up),
+            ("speed-down", self.speed_down),
+            ("turbo", self.turbo),
+            ("counters", self.counters),
+            ("cheats", self.cheats),
+            ("menu", self.menu),
+            ("quit", self.quit),
+        ]
+    }
+}
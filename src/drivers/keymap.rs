@@ -0,0 +1,408 @@
+use sdl2::controller::{Axis, Button};
+use sdl2::keyboard::Scancode;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use super::gamepad::{PadInput, DEFAULT_DEADZONE, MAX_PLAYERS};
+use super::hotkeys::Hotkeys;
+
+// chip8 keys in the order they appear on the hex keypad, row by row
+//  1 2 3 C
+//  4 5 6 D
+//  7 8 9 E
+//  A 0 B F
+pub const KEYPAD_LAYOUT: [usize; 16] = [
+    0x1, 0x2, 0x3, 0xc, 0x4, 0x5, 0x6, 0xd, 0x7, 0x8, 0x9, 0xe, 0xa, 0x0, 0xb, 0xf,
+];
+
+// scancodes describe the physical position of a key, so this block of keys is the same on
+// qwerty, azerty and dvorak keyboards even though the printed letters differ
+const LEFT_BLOCK: [Scancode; 16] = [
+    Scancode::Num1,
+    Scancode::Num2,
+    Scancode::Num3,
+    Scancode::Num4,
+    Scancode::Q,
+    Scancode::W,
+    Scancode::E,
+    Scancode::R,
+    Scancode::A,
+    Scancode::S,
+    Scancode::D,
+    Scancode::F,
+    Scancode::Z,
+    Scancode::X,
+    Scancode::C,
+    Scancode::V,
+];
+
+// maps physical host keys and controller inputs to chip8 keys. a chip8 key can have any number
+// of host keys
+#[derive(Clone)]
+pub struct Keymap {
+    bindings: HashMap<Scancode, usize>,
+    pad_bindings: HashMap<PadInput, usize>,
+    // how far an analog stick has to be pushed before it counts as pressed
+    deadzone: i16,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self {
+            bindings: HashMap::new(),
+            pad_bindings: HashMap::new(),
+            deadzone: DEFAULT_DEADZONE,
+        }
+    }
+}
+
+impl Keymap {
+    pub fn empty() -> Self {
+        Self::default()
+    }
+
+    // the usual 4x4 block under the number row
+    pub fn qwerty() -> Self {
+        let mut keymap = Self::empty();
+        for (scancode, key) in LEFT_BLOCK.iter().zip(KEYPAD_LAYOUT.iter()) {
+            keymap.bind(*scancode, *key);
+        }
+        keymap.bind_default_pads();
+        keymap
+    }
+
+    // numpad digits map to the same chip8 digits, the remaining numpad keys cover A - F
+    pub fn numpad() -> Self {
+        let mut keymap = Self::empty();
+        let digits = [
+            Scancode::Kp0,
+            Scancode::Kp1,
+            Scancode::Kp2,
+            Scancode::Kp3,
+            Scancode::Kp4,
+            Scancode::Kp5,
+            Scancode::Kp6,
+            Scancode::Kp7,
+            Scancode::Kp8,
+            Scancode::Kp9,
+            Scancode::KpPeriod,
+            Scancode::KpEnter,
+            Scancode::KpPlus,
+            Scancode::KpMinus,
+            Scancode::KpMultiply,
+            Scancode::KpDivide,
+        ];
+        for (key, scancode) in digits.iter().enumerate() {
+            keymap.bind(*scancode, key);
+        }
+        keymap.bind_default_pads();
+        keymap
+    }
+
+    // the left player keeps the qwerty block (1 and 4 move the left paddle in PONG), the right
+    // player gets the arrow keys on C and D. with controllers, the first one drives 1 and 4 and
+    // the second one C and D
+    pub fn two_player() -> Self {
+        let mut keymap = Self::qwerty();
+        keymap.bind(Scancode::Up, 0xc);
+        keymap.bind(Scancode::Down, 0xd);
+        keymap.bind(Scancode::Left, 0xe);
+        keymap.bind(Scancode::Right, 0xf);
+
+        keymap.pad_bindings.clear();
+        for (player, (up, down)) in [(0x1, 0x4), (0xc, 0xd)].iter().enumerate() {
+            keymap.bind_pad(PadInput::button(player, Button::DPadUp), *up);
+            keymap.bind_pad(PadInput::axis(player, Axis::LeftY, false), *up);
+            keymap.bind_pad(PadInput::button(player, Button::DPadDown), *down);
+            keymap.bind_pad(PadInput::axis(player, Axis::LeftY, true), *down);
+        }
+        keymap
+    }
+
+    // every controller gets the same layout: the d-pad and left stick move on 2/4/6/8, which
+    // most games use as directions, and the face buttons cover 5, 0, A and B
+    fn bind_default_pads(&mut self) {
+        for player in 0..MAX_PLAYERS {
+            let directions = [
+                (Button::DPadUp, Axis::LeftY, false, 0x2),
+                (Button::DPadDown, Axis::LeftY, true, 0x8),
+                (Button::DPadLeft, Axis::LeftX, false, 0x4),
+                (Button::DPadRight, Axis::LeftX, true, 0x6),
+            ];
+            for (button, axis, positive, key) in directions {
+                self.bind_pad(PadInput::button(player, button), key);
+                self.bind_pad(PadInput::axis(player, axis, positive), key);
+            }
+            self.bind_pad(PadInput::button(player, Button::A), 0x5);
+            self.bind_pad(PadInput::button(player, Button::B), 0x0);
+            self.bind_pad(PadInput::button(player, Button::X), 0xa);
+            self.bind_pad(PadInput::button(player, Button::Y), 0xb);
+        }
+    }
+
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "qwerty" => Some(Self::qwerty()),
+            "numpad" => Some(Self::numpad()),
+            "two-player" => Some(Self::two_player()),
+            _ => None,
+        }
+    }
+
+    pub fn bind(&mut self, scancode: Scancode, key: usize) {
+        self.bindings.insert(scancode, key & 0xf);
+    }
+
+    pub fn bind_pad(&mut self, input: PadInput, key: usize) {
+        self.pad_bindings.insert(input, key & 0xf);
+    }
+
+    pub fn set_deadzone(&mut self, deadzone: i16) {
+        self.deadzone = deadzone;
+    }
+
+    pub fn deadzone(&self) -> i16 {
+        self.deadzone
+    }
+
+    // remove every keyboard binding, controller bindings are kept
+    pub fn clear_keys(&mut self) {
+        self.bindings.clear();
+    }
+
+    // remove every host key and controller input bound to a chip8 key
+    pub fn unbind_key(&mut self, key: usize) {
+        self.bindings.retain(|_, bound| *bound != key);
+        self.pad_bindings.retain(|_, bound| *bound != key);
+    }
+
+    pub fn key_for(&self, scancode: Scancode) -> Option<usize> {
+        self.bindings.get(&scancode).copied()
+    }
+
+    pub fn pad_key_for(&self, input: PadInput) -> Option<usize> {
+        self.pad_bindings.get(&input).copied()
+    }
+
+    pub fn pad_inputs_for(&self, key: usize) -> Vec<PadInput> {
+        let mut inputs: Vec<PadInput> = self
+            .pad_bindings
+            .iter()
+            .filter(|(_, bound)| **bound == key)
+            .map(|(input, _)| *input)
+            .collect();
+        inputs.sort_by_key(PadInput::name);
+        inputs
+    }
+
+    pub fn scancodes_for(&self, key: usize) -> Vec<Scancode> {
+        let mut scancodes: Vec<Scancode> = self
+            .bindings
+            .iter()
+            .filter(|(_, bound)| **bound == key)
+            .map(|(scancode, _)| *scancode)
+            .collect();
+        scancodes.sort_by_key(|scancode| *scancode as i32);
+        scancodes
+    }
+}
+
+// keymap config file. sections are named after the ROM file they apply to, [default] is used for
+// every other ROM. a section can start from a preset and then list the host keys for each chip8
+// key using SDL scancode names. controller inputs are written pad<player>:<input> with the names
+// SDL uses in controller mappings, analog axes take a + or - for the direction:
+//
+//  [default]
+//  preset = qwerty
+//  deadzone = 8000
+//
+//  [PONG]
+//  preset = two-player
+//  1 = W, Keypad 8, pad0:dpup, pad0:lefty-
+//  4 = S, Keypad 2, pad0:dpdown, pad0:lefty+
+//
+// the emulator controls are set in their own section, which applies to every ROM:
+//
+//  [hotkeys]
+//  pause = P
+//  turbo = Tab
+#[derive(Clone, Default)]
+pub struct KeymapConfig {
+    default: Option<Keymap>,
+    roms: HashMap<String, Keymap>,
+    hotkeys: Hotkeys,
+}
+
+impl KeymapConfig {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text =
+            fs::read_to_string(path).map_err(|e| format!("can't read keymap {}: {}", path, e))?;
+        Self::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut config = Self::default();
+        let mut section: Option<(String, Keymap)> = None;
+        let mut in_hotkeys = false;
+
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if line.starts_with('[') && line.ends_with(']') {
+                if let Some((name, keymap)) = section.take() {
+                    config.set(&name, keymap);
+                }
+                let name = line[1..line.len() - 1].trim();
+                in_hotkeys = name == "hotkeys";
+                if !in_hotkeys {
+                    section = Some((name.to_string(), Keymap::empty()));
+                }
+                continue;
+            }
+
+            let (lhs, rhs) = line
+                .split_once('=')
+                .ok_or(format!("line {}: expected `key = scancodes`", n + 1))?;
+            let (lhs, rhs) = (lhs.trim(), rhs.trim());
+
+            if in_hotkeys {
+                let scancode = Scancode::from_name(rhs).ok_or(format!(
+                    "line {}: unknown scancode {}",
+                    n + 1,
+                    rhs
+                ))?;
+                config.hotkeys.set(lhs, scancode).ok_or(format!(
+                    "line {}: unknown hotkey {}",
+                    n + 1,
+                    lhs
+                ))?;
+                continue;
+            }
+            let (_, keymap) = section
+                .as_mut()
+                .ok_or(format!("line {}: binding outside of a section", n + 1))?;
+
+            if lhs == "preset" {
+                *keymap =
+                    Keymap::preset(rhs).ok_or(format!("line {}: unknown preset {}", n + 1, rhs))?;
+                continue;
+            }
+            if lhs == "deadzone" {
+                let deadzone = rhs
+                    .parse::<i16>()
+                    .ok()
+                    .filter(|deadzone| *deadzone >= 0)
+                    .ok_or(format!("line {}: bad deadzone {}", n + 1, rhs))?;
+                keymap.set_deadzone(deadzone);
+                continue;
+            }
+            let key = usize::from_str_radix(lhs, 16)
+                .ok()
+                .filter(|key| *key < 16)
+                .ok_or(format!("line {}: {} is not a chip8 key", n + 1, lhs))?;
+            keymap.unbind_key(key);
+            for name in rhs
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+            {
+                if name.starts_with("pad") {
+                    let input = PadInput::from_name(name).ok_or(format!(
+                        "line {}: unknown controller input {}",
+                        n + 1,
+                        name
+                    ))?;
+                    keymap.bind_pad(input, key);
+                    continue;
+                }
+                let scancode = Scancode::from_name(name).ok_or(format!(
+                    "line {}: unknown scancode {}",
+                    n + 1,
+                    name
+                ))?;
+                keymap.bind(scancode, key);
+            }
+        }
+        if let Some((name, keymap)) = section {
+            config.set(&name, keymap);
+        }
+        Ok(config)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        fs::write(path, self.to_string()).map_err(|e| format!("can't write keymap {}: {}", path, e))
+    }
+
+    pub fn set(&mut self, section: &str, keymap: Keymap) {
+        if section == "default" {
+            self.default = Some(keymap);
+        } else {
+            self.roms.insert(section.to_string(), keymap);
+        }
+    }
+
+    pub fn hotkeys(&self) -> &Hotkeys {
+        &self.hotkeys
+    }
+
+    // keymap for a ROM, looked up by its file name
+    pub fn for_rom(&self, rom_path: &str) -> Keymap {
+        let name = Self::section_name(rom_path);
+        self.roms
+            .get(&name)
+            .or(self.default.as_ref())
+            .cloned()
+            .unwrap_or_else(Keymap::qwerty)
+    }
+
+    pub fn section_name(rom_path: &str) -> String {
+        Path::new(rom_path)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| rom_path.to_string())
+    }
+
+    fn write_section(out: &mut String, name: &str, keymap: &Keymap) {
+        out.push_str(&format!("[{}]\n", name));
+        if keymap.deadzone() != DEFAULT_DEADZONE {
+            out.push_str(&format!("deadzone = {}\n", keymap.deadzone()));
+        }
+        for key in 0..16 {
+            let mut names: Vec<String> = keymap
+                .scancodes_for(key)
+                .iter()
+                .map(|scancode| scancode.name().to_string())
+                .collect();
+            names.extend(keymap.pad_inputs_for(key).iter().map(PadInput::name));
+            if !names.is_empty() {
+                out.push_str(&format!("{:x} = {}\n", key, names.join(", ")));
+            }
+        }
+        out.push('\n');
+    }
+}
+
+impl std::fmt::Display for KeymapConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mut out = String::new();
+        if self.hotkeys != Hotkeys::default() {
+            out.push_str("[hotkeys]\n");
+            for (name, scancode) in self.hotkeys.bindings() {
+                out.push_str(&format!("{} = {}\n", name, scancode.name()));
+            }
+            out.push('\n');
+        }
+        if let Some(keymap) = &self.default {
+            Self::write_section(&mut out, "default", keymap);
+        }
+        let mut names: Vec<&String> = self.roms.keys().collect();
+        names.sort();
+        for name in names {
+            Self::write_section(&mut out, name, &self.roms[name]);
+        }
+        write!(f, "{}", out)
+    }
+}
@@ -1,8 +1,33 @@
+use sdl2::controller::{Button, GameController};
 use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
+use sdl2::keyboard::Scancode;
+use std::collections::VecDeque;
+
+use super::gamepad::Gamepads;
+use super::hotkeys::{Hotkey, Hotkeys};
+use super::keymap::Keymap;
+use crate::{KeyEvent, KeyEvents};
+
+// navigation in the rom browser, from the arrow keys or the d-pad
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum MenuInput {
+    Up,
+    Down,
+    Select,
+    Quit,
+}
 
 pub struct Keypad {
     keypad: [bool; 16],
+    key_events: KeyEvents,
+    hotkeys: Hotkeys,
+    hotkey_events: VecDeque<Hotkey>,
+    turbo: bool,
+    keymap: Keymap,
+    // controllers are closed when dropped, so keep the open ones around
+    controller_sub: sdl2::GameControllerSubsystem,
+    controllers: Vec<GameController>,
+    gamepads: Gamepads,
     events: sdl2::EventPump,
 }
 
@@ -10,6 +35,14 @@ impl Keypad {
     pub fn new(sdl_context: &sdl2::Sdl) -> Self {
         Self {
             keypad: [false; 16],
+            key_events: KeyEvents::default(),
+            hotkeys: Hotkeys::default(),
+            hotkey_events: VecDeque::new(),
+            turbo: false,
+            keymap: Keymap::qwerty(),
+            controller_sub: sdl_context.game_controller().unwrap(),
+            controllers: Vec::new(),
+            gamepads: Gamepads::default(),
             events: sdl_context.event_pump().unwrap(),
         }
     }
@@ -22,49 +55,170 @@ impl Keypad {
         self.keypad[index]
     }
 
+    // the next key press or release since the keypad was cleared, oldest first
+    pub fn pop_key_event(&mut self) -> Option<KeyEvent> {
+        self.key_events.pop()
+    }
+
+    pub fn pop_hotkey(&mut self) -> Option<Hotkey> {
+        self.hotkey_events.pop_front()
+    }
+
+    pub fn is_turbo_held(&self) -> bool {
+        self.turbo
+    }
+
+    pub fn set_hotkeys(&mut self, hotkeys: Hotkeys) {
+        self.hotkeys = hotkeys;
+    }
+
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.keymap = keymap;
+    }
+
+    pub fn keymap(&self) -> &Keymap {
+        &self.keymap
+    }
+
+    // feed an event through the same path as the ones polled from SDL. used by tests to drive the
+    // keypad with synthetic controller events, see connect_virtual_controller
+    pub fn inject_event(&mut self, event: Event) {
+        self.handle_controller_event(&event);
+        self.update_keypad();
+    }
+
+    // connect a controller that only exists for inject_event, its events use instance_id as
+    // which. SDL counts instance ids up from 0, so pick one far above the real controllers.
+    // false when all the players are taken
+    pub fn connect_virtual_controller(&mut self, instance_id: u32) -> bool {
+        self.gamepads.connect(instance_id).is_some()
+    }
+
+    fn handle_controller_event(&mut self, event: &Event) {
+        match *event {
+            Event::ControllerDeviceAdded { which, .. } => match self.controller_sub.open(which) {
+                Ok(controller) => {
+                    if self.gamepads.connect(controller.instance_id()).is_some() {
+                        self.controllers.push(controller);
+                    }
+                }
+                // a controller SDL lists but can't open, e.g. one unplugged again already
+                Err(e) => eprintln!("can't open controller {}: {}", which, e),
+            },
+            Event::ControllerDeviceRemoved { which, .. } => {
+                self.gamepads.disconnect(which);
+                self.controllers
+                    .retain(|controller| controller.instance_id() != which);
+            }
+            _ => self.gamepads.handle_event(event),
+        }
+    }
+
+    // block until a key is pressed and return its scancode. returns None if the window is
+    // closed or escape is pressed
+    pub fn wait_for_scancode(&mut self) -> Option<Scancode> {
+        loop {
+            match self.events.wait_event() {
+                Event::Quit { .. } => return None,
+                Event::KeyDown {
+                    scancode: Some(Scancode::Escape),
+                    ..
+                } => return None,
+                Event::KeyDown {
+                    scancode: Some(scancode),
+                    repeat: false,
+                    ..
+                } => return Some(scancode),
+                _ => {}
+            }
+        }
+    }
+
     pub fn clear_keyboard(&mut self) {
         self.keypad = [false; 16];
+        self.key_events.clear();
     }
 
     pub fn poll(&mut self) {
-        for each in self.events.poll_iter() {
-            if let Event::Quit { .. } = each {
-                println!("exiting");
-                std::process::exit(0);
+        let events: Vec<Event> = self.events.poll_iter().collect();
+        for each in events {
+            match each {
+                // closing the window is handled like the quit hotkey, so the host gets to shut
+                // down cleanly
+                Event::Quit { .. } => self.hotkey_events.push_back(Hotkey::Quit),
+                Event::KeyDown {
+                    scancode: Some(scancode),
+                    repeat: false,
+                    ..
+                } => {
+                    if let Some(hotkey) = self.hotkeys.hotkey_for(scancode) {
+                        self.hotkey_events.push_back(hotkey);
+                    }
+                }
+                _ => self.handle_controller_event(&each),
             }
         }
+        self.update_keypad();
+    }
 
-        // collself.media.display.set_drawflag(false);ect all the keys pressed
-        let keys: Vec<Keycode> = self
+    // poll for menu navigation instead of chip8 keys. escape or closing the window quits
+    pub fn poll_menu(&mut self) -> Vec<MenuInput> {
+        let events: Vec<Event> = self.events.poll_iter().collect();
+        let mut inputs = Vec::new();
+        for each in events {
+            let input = match each {
+                Event::Quit { .. } => Some(MenuInput::Quit),
+                Event::KeyDown {
+                    scancode: Some(scancode),
+                    ..
+                } => match scancode {
+                    Scancode::Up => Some(MenuInput::Up),
+                    Scancode::Down => Some(MenuInput::Down),
+                    Scancode::Return | Scancode::KpEnter => Some(MenuInput::Select),
+                    Scancode::Escape => Some(MenuInput::Quit),
+                    _ => None,
+                },
+                Event::ControllerButtonDown { button, .. } => match button {
+                    Button::DPadUp => Some(MenuInput::Up),
+                    Button::DPadDown => Some(MenuInput::Down),
+                    Button::A | Button::Start => Some(MenuInput::Select),
+                    _ => None,
+                },
+                _ => {
+                    self.handle_controller_event(&each);
+                    None
+                }
+            };
+            inputs.extend(input);
+        }
+        inputs
+    }
+
+    fn update_keypad(&mut self) {
+        self.turbo = self
+            .events
+            .keyboard_state()
+            .is_scancode_pressed(self.hotkeys.turbo);
+
+        // collect all the keys pressed
+        let mut keys: Vec<usize> = self
             .events
             .keyboard_state()
             .pressed_scancodes()
-            .filter_map(Keycode::from_scancode)
+            .filter_map(|scancode| self.keymap.key_for(scancode))
             .collect();
+        keys.extend(
+            self.gamepads
+                .active_inputs(self.keymap.deadzone())
+                .into_iter()
+                .filter_map(|input| self.keymap.pad_key_for(input)),
+        );
 
-        for key in keys {
-            let key_index = match key {
-                Keycode::Num1 => Some(1),
-                Keycode::Num2 => Some(2),
-                Keycode::Num3 => Some(3),
-                Keycode::Num4 => Some(0xc),
-                Keycode::Q => Some(4),
-                Keycode::W => Some(5),
-                Keycode::E => Some(6),
-                Keycode::R => Some(0xd),
-                Keycode::A => Some(7),
-                Keycode::S => Some(8),
-                Keycode::D => Some(9),
-                Keycode::F => Some(0xe),
-                Keycode::Z => Some(0xa),
-                Keycode::X => Some(0x0),
-                Keycode::C => Some(0xb),
-                Keycode::V => Some(0xf),
-                _ => None,
-            };
-            if let Some(index) = key_index {
-                self.keypad[index] = true;
-            }
+        // the state is rebuilt on every poll so released keys go back to false
+        self.keypad = [false; 16];
+        for index in keys {
+            self.keypad[index] = true;
         }
+        self.key_events.update(&self.keypad);
     }
 }
@@ -1,7 +1,17 @@
 mod audio;
+mod gamepad;
+mod hotkeys;
+mod keymap;
 mod keypad;
+mod osd;
 mod video;
+mod wav;
 
 pub use self::audio::Audio;
-pub use self::keypad::Keypad;
+pub use self::gamepad::{PadControl, PadInput};
+pub use self::hotkeys::{Hotkey, Hotkeys};
+pub use self::keymap::{Keymap, KeymapConfig, KEYPAD_LAYOUT};
+pub use self::keypad::{Keypad, MenuInput};
+pub use self::osd::Osd;
 pub use self::video::Video;
+pub use self::wav::WavWriter;
@@ -0,0 +1,180 @@
+use std::time::{Duration, Instant};
+
+// how long a message stays on screen
+const MESSAGE_DURATION: Duration = Duration::from_secs(2);
+
+// built-in 3x5 font, one row per byte, low 3 bits used, msb on the left
+pub const GLYPH_WIDTH: usize = 3;
+pub const GLYPH_HEIGHT: usize = 5;
+
+pub fn glyph(c: char) -> [u8; GLYPH_HEIGHT] {
+    match c.to_ascii_uppercase() {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        ' ' => [0b000, 0b000, 0b000, 0b000, 0b000],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        '=' => [0b000, 0b111, 0b000, 0b111, 0b000],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '%' => [0b101, 0b001, 0b010, 0b100, 0b101],
+        '!' => [0b010, 0b010, 0b010, 0b000, 0b010],
+        '(' => [0b001, 0b010, 0b010, 0b010, 0b001],
+        ')' => [0b100, 0b010, 0b010, 0b010, 0b100],
+        '[' => [0b011, 0b010, 0b010, 0b010, 0b011],
+        ']' => [0b110, 0b010, 0b010, 0b010, 0b110],
+        '>' => [0b100, 0b010, 0b001, 0b010, 0b100],
+        '<' => [0b001, 0b010, 0b100, 0b010, 0b001],
+        _ => [0b111, 0b001, 0b010, 0b000, 0b010],
+    }
+}
+
+// text shown over the chip8 screen. it is only ever drawn on the canvas, the emulated screen
+// buffer never sees it
+pub struct Osd {
+    messages: Vec<(String, Instant)>,
+    // persistent state like "paused", shown for as long as it is set
+    status: Option<String>,
+    preset: Option<String>,
+    // text drawn by a script, shown until it draws something else
+    overlay: Vec<String>,
+    show_counters: bool,
+    fps: f32,
+    ips: f32,
+    frames: u32,
+    instructions: u64,
+    window_start: Instant,
+    // lines drawn last time, to know when the overlay changed
+    drawn: Vec<String>,
+}
+
+impl Default for Osd {
+    fn default() -> Self {
+        Self {
+            messages: Vec::new(),
+            status: None,
+            preset: None,
+            overlay: Vec::new(),
+            show_counters: false,
+            fps: 0.0,
+            ips: 0.0,
+            frames: 0,
+            instructions: 0,
+            window_start: Instant::now(),
+            drawn: Vec::new(),
+        }
+    }
+}
+
+impl Osd {
+    pub fn show_message(&mut self, text: &str) {
+        self.messages.push((text.to_string(), Instant::now()));
+    }
+
+    pub fn set_status(&mut self, status: Option<String>) {
+        self.status = status;
+    }
+
+    pub fn set_preset(&mut self, preset: Option<String>) {
+        self.preset = preset;
+    }
+
+    pub fn set_overlay(&mut self, lines: Vec<String>) {
+        self.overlay = lines;
+    }
+
+    pub fn set_show_counters(&mut self, show: bool) {
+        self.show_counters = show;
+    }
+
+    pub fn is_showing_counters(&self) -> bool {
+        self.show_counters
+    }
+
+    // called once per host frame with the number of instructions executed during it. the
+    // counters are averaged over one second
+    pub fn count_frame(&mut self, instructions: u32) {
+        self.frames += 1;
+        self.instructions += instructions as u64;
+        let elapsed = self.window_start.elapsed();
+        if elapsed >= Duration::from_secs(1) {
+            let seconds = elapsed.as_secs_f32();
+            self.fps = self.frames as f32 / seconds;
+            self.ips = self.instructions as f32 / seconds;
+            self.frames = 0;
+            self.instructions = 0;
+            self.window_start = Instant::now();
+        }
+    }
+
+    // the messages that haven't timed out yet, oldest first
+    pub fn messages(&mut self) -> Vec<String> {
+        self.messages
+            .retain(|(_, shown)| shown.elapsed() < MESSAGE_DURATION);
+        self.messages.iter().map(|(text, _)| text.clone()).collect()
+    }
+
+    // the lines to show right now, top to bottom
+    pub fn lines(&mut self) -> Vec<String> {
+        let messages = self.messages();
+
+        let mut lines = Vec::new();
+        if self.show_counters {
+            lines.push(format!("{:.0} FPS  {:.0} IPS", self.fps, self.ips));
+            if let Some(preset) = &self.preset {
+                lines.push(format!("QUIRKS: {}", preset));
+            }
+        }
+        lines.extend(self.overlay.iter().cloned());
+        if let Some(status) = &self.status {
+            lines.push(status.clone());
+        }
+        lines.extend(messages);
+        lines
+    }
+
+    // true when the overlay looks different from the last time it was drawn
+    pub fn needs_redraw(&mut self) -> bool {
+        self.lines() != self.drawn
+    }
+
+    pub fn set_drawn(&mut self, lines: Vec<String>) {
+        self.drawn = lines;
+    }
+}
@@ -1,15 +1,22 @@
 use sdl2::render::Canvas;
 use sdl2::{self, pixels, rect::Rect};
 
+use super::osd::{self, Osd};
+
 pub const SCREEN_WIDTH: usize = 64;
 pub const SCREEN_HEIGHT: usize = 32;
 
 const SCALE_FACTOR: usize = 15;
 
+// size of an osd font pixel in window pixels, and the gap around text
+const OSD_SCALE: usize = 3;
+const OSD_MARGIN: usize = 6;
+
 pub struct Video {
     draw: bool,
     canvas: Canvas<sdl2::video::Window>,
-    screen: [bool; (SCREEN_WIDTH * SCREEN_HEIGHT) as usize],
+    screen: [bool; SCREEN_WIDTH * SCREEN_HEIGHT],
+    pub osd: Osd,
 }
 
 impl Video {
@@ -36,6 +43,7 @@ impl Video {
             draw: false,
             canvas,
             screen: [false; SCREEN_WIDTH * SCREEN_HEIGHT],
+            osd: Osd::default(),
         }
     }
 
@@ -73,15 +81,120 @@ impl Video {
                 let _ = self.canvas.fill_rect(rect);
             }
         }
+        self.draw_osd();
         self.canvas.present();
         self.draw = false;
     }
 
+    // the osd goes straight onto the canvas, on top of the scaled chip8 pixels, one line of
+    // text per row starting from the top left corner
+    fn draw_osd(&mut self) {
+        let lines = self.osd.lines();
+        for (row, line) in lines.iter().enumerate() {
+            self.draw_text(row, line, false);
+        }
+        self.osd.set_drawn(lines);
+    }
+
+    // draw a line of text with the osd font at the given text row. highlighted text is drawn
+    // black on white
+    fn draw_text(&mut self, row: usize, text: &str, highlight: bool) {
+        let line_height = (osd::GLYPH_HEIGHT + 2) * OSD_SCALE;
+        let x_origin = OSD_MARGIN;
+        let y_origin = OSD_MARGIN + row * (line_height + OSD_SCALE);
+        let (background, foreground) = if highlight {
+            (
+                pixels::Color::RGB(255, 255, 255),
+                pixels::Color::RGB(0, 0, 0),
+            )
+        } else {
+            (
+                pixels::Color::RGB(32, 32, 32),
+                pixels::Color::RGB(255, 255, 255),
+            )
+        };
+
+        let width = text.chars().count() * (osd::GLYPH_WIDTH + 1) * OSD_SCALE + OSD_SCALE;
+        self.canvas.set_draw_color(background);
+        let _ = self.canvas.fill_rect(Rect::new(
+            x_origin as i32,
+            y_origin as i32,
+            width as u32,
+            line_height as u32,
+        ));
+
+        self.canvas.set_draw_color(foreground);
+        for (n, c) in text.chars().enumerate() {
+            let glyph_x = x_origin + OSD_SCALE + n * (osd::GLYPH_WIDTH + 1) * OSD_SCALE;
+            for (y, bits) in osd::glyph(c).iter().enumerate() {
+                for x in 0..osd::GLYPH_WIDTH {
+                    if bits & (0b100 >> x) == 0 {
+                        continue;
+                    }
+                    let _ = self.canvas.fill_rect(Rect::new(
+                        (glyph_x + x * OSD_SCALE) as i32,
+                        (y_origin + OSD_SCALE + y * OSD_SCALE) as i32,
+                        OSD_SCALE as u32,
+                        OSD_SCALE as u32,
+                    ));
+                }
+            }
+        }
+    }
+
+    // number of text rows that fit in the window
+    pub fn text_rows(&self) -> usize {
+        let line_height = (osd::GLYPH_HEIGHT + 2) * OSD_SCALE + OSD_SCALE;
+        (SCREEN_HEIGHT * SCALE_FACTOR - 2 * OSD_MARGIN) / line_height
+    }
+
+    // draw a full window menu, replacing the chip8 screen until the next draw_screen. the
+    // selected item is highlighted and the osd messages go in the bottom rows
+    pub fn draw_menu(&mut self, title: &str, items: &[String], selected: usize) {
+        self.canvas.set_draw_color(pixels::Color::RGB(0, 0, 0));
+        self.canvas.clear();
+        self.draw_text(0, title, false);
+
+        let rows = self.text_rows();
+        let messages = self.osd.messages();
+        let messages = &messages[messages.len().saturating_sub(rows / 2)..];
+        for (n, message) in messages.iter().enumerate() {
+            self.draw_text(rows - messages.len() + n, message, false);
+        }
+
+        // scroll so the selected item stays visible below the title
+        let visible = rows.saturating_sub(2 + messages.len()).max(1);
+        let first = selected.saturating_sub(visible - 1);
+        for (row, item) in items.iter().enumerate().skip(first).take(visible) {
+            self.draw_text(row - first + 2, item, row == selected);
+        }
+        self.canvas.present();
+    }
+
+    // true when the canvas is out of date, either because the chip8 drew or the osd changed
+    pub fn needs_redraw(&mut self) -> bool {
+        self.draw || self.osd.needs_redraw()
+    }
+
+    pub fn set_title(&mut self, title: &str) {
+        let _ = self.canvas.window_mut().set_title(title);
+    }
+
     pub fn clear_screen(&mut self) {
         self.screen = [false; SCREEN_WIDTH * SCREEN_HEIGHT];
         self.draw_screen();
     }
 
+    pub fn screen(&self) -> &[bool] {
+        &self.screen
+    }
+
+    // replace the whole screen, e.g. when loading a save state
+    pub fn set_screen(&mut self, pixels: &[bool]) {
+        self.screen.copy_from_slice(pixels);
+        self.draw = true;
+    }
+
     pub fn get_screen_pixel_state(&mut self, x: usize, y: usize) -> bool {
         self.screen[Video::calculate_index(x, y)]
     }
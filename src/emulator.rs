@@ -0,0 +1,426 @@
+use std::time::{Duration, Instant};
+
+use crate::cheats::{Cheat, CheatFile, Mode};
+use crate::drivers::{self, Hotkey, Keymap, MenuInput};
+#[cfg(feature = "scripting")]
+use crate::script::Script;
+use crate::server::Server;
+use crate::{
+    romdb, Chip8, Chip8Error, FONTSET, MAX_SPEED, SCREEN_HEIGHT, SCREEN_WIDTH, TURBO_SPEED,
+};
+
+const FRAME_DURATION: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+pub struct Media {
+    pub sound: drivers::Audio,
+
+    // the window, showing a copy of the chip8 screen with the osd on top
+    pub display: drivers::Video,
+
+    // keyboard and gamepads, mapped to the hex keypad
+    pub keypad: drivers::Keypad,
+}
+
+// the SDL host around the interpreter: window, keyboard and gamepads, the buzzer, hotkeys and
+// 60Hz pacing
+pub struct Emulator {
+    pub chip8: Chip8,
+    pub media: Media,
+
+    speed: u32,
+    paused: bool,
+    // false once quit was requested, the host loop should stop and shut down
+    running: bool,
+    // set by the menu hotkey, the host should go back to the rom browser
+    menu_requested: bool,
+    next_frame: Instant,
+    // JSON-RPC control server, see attach_server
+    server: Option<Server>,
+    // cheats for every ROM, the ones for rom_hash are applied
+    cheats: Option<CheatFile>,
+    rom_hash: u32,
+    // runs the frames while attached, see attach_script
+    #[cfg(feature = "scripting")]
+    script: Option<Script>,
+}
+
+impl Emulator {
+    pub fn new(sdl_context: &sdl2::Sdl) -> Self {
+        Self::with_audio(sdl_context, drivers::Audio::new(sdl_context))
+    }
+
+    // use an already set up audio backend, e.g. a headless one when there is no audio device
+    pub fn with_audio(sdl_context: &sdl2::Sdl, sound: drivers::Audio) -> Self {
+        Self {
+            chip8: Chip8::new(),
+            media: Media {
+                sound,
+                display: drivers::Video::new(sdl_context),
+                keypad: drivers::Keypad::new(sdl_context),
+            },
+            speed: 1,
+            paused: false,
+            running: true,
+            menu_requested: false,
+            next_frame: Instant::now(),
+            server: None,
+            cheats: None,
+            rom_hash: 0,
+            #[cfg(feature = "scripting")]
+            script: None,
+        }
+    }
+
+    // load the game to memory and start it. on errors the machine is left as it was
+    pub fn load_and_init(&mut self, filepath: &str) -> Result<(), String> {
+        let rom = std::fs::read(filepath).map_err(|e| format!("can't read {}: {}", filepath, e))?;
+        self.chip8.load_rom(&rom).map_err(|e| e.to_string())?;
+        self.rom_hash = romdb::rom_hash(&rom);
+        self.media.keypad.clear_keyboard();
+        Ok(())
+    }
+
+    pub fn reset(&mut self) {
+        self.chip8.reset();
+        self.media.keypad.clear_keyboard();
+    }
+
+    // load a new build of the ROM from disk and restart it, leaving the keymap and window alone.
+    // see Chip8::reload_rom
+    pub fn reload_rom(
+        &mut self,
+        filepath: &str,
+        keep_rpl: bool,
+        keep_state: bool,
+    ) -> std::io::Result<()> {
+        let rom = std::fs::read(filepath)?;
+        self.chip8
+            .reload_rom(&rom, keep_rpl, keep_state)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?;
+        self.rom_hash = romdb::rom_hash(&rom);
+        Ok(())
+    }
+
+    // let clients of the control server drive the machine. their requests are handled at the
+    // start of every host frame, their keys are held on top of the keyboard
+    pub fn attach_server(&mut self, server: Server) {
+        self.server = Some(server);
+    }
+
+    // cheats to offer in the cheat menu, the ones for the running ROM are picked by its hash
+    pub fn set_cheats(&mut self, cheats: CheatFile) {
+        self.cheats = Some(cheats);
+    }
+
+    // run the frames through a script, its keys are held on top of the keyboard and its text is
+    // drawn over the screen. a script that fails is reported and dropped
+    #[cfg(feature = "scripting")]
+    pub fn attach_script(&mut self, script: Script) {
+        self.script = Some(script);
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    // true once after the menu hotkey was pressed
+    pub fn take_menu_request(&mut self) -> bool {
+        if self.menu_requested {
+            self.media.sound.mute();
+        }
+        std::mem::replace(&mut self.menu_requested, false)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    // emulated frames run per host frame, counting turbo
+    pub fn effective_speed(&self) -> u32 {
+        if self.media.keypad.is_turbo_held() {
+            self.speed * TURBO_SPEED
+        } else {
+            self.speed
+        }
+    }
+
+    // run one host frame: poll input, handle the hotkeys, run as many emulated frames as the
+    // speed asks for, draw and sleep until the next frame is due
+    pub fn emulate_frame(&mut self) {
+        self.media.keypad.poll();
+        while let Some(event) = self.media.keypad.pop_key_event() {
+            if let Some(server) = &mut self.server {
+                server.key_event(event);
+            }
+        }
+        if let Some(server) = &mut self.server {
+            server.poll(&mut self.chip8, &mut self.paused);
+        }
+        for key in 0..self.media.keypad.get_keypad_len() {
+            let pressed = self.media.keypad.is_key_pressed(key)
+                || self
+                    .server
+                    .as_ref()
+                    .is_some_and(|server| server.is_key_pressed(key));
+            #[cfg(feature = "scripting")]
+            let pressed = pressed
+                || self
+                    .script
+                    .as_ref()
+                    .is_some_and(|script| script.is_key_pressed(key));
+            self.chip8.set_key(key, pressed);
+        }
+
+        let mut advance = false;
+        while let Some(hotkey) = self.media.keypad.pop_hotkey() {
+            let osd = &mut self.media.display.osd;
+            match hotkey {
+                Hotkey::Pause => {
+                    self.paused = !self.paused;
+                    if !self.paused {
+                        osd.show_message("Resumed");
+                    }
+                }
+                Hotkey::Reset => {
+                    self.reset();
+                    self.media.display.osd.show_message("Reset");
+                }
+                Hotkey::FrameAdvance => advance = self.paused,
+                Hotkey::SpeedUp => {
+                    self.speed = (self.speed + 1).min(MAX_SPEED);
+                    osd.show_message(&format!("Speed x{}", self.speed));
+                }
+                Hotkey::SpeedDown => {
+                    self.speed = (self.speed - 1).max(1);
+                    osd.show_message(&format!("Speed x{}", self.speed));
+                }
+                Hotkey::ToggleCounters => {
+                    let show = !osd.is_showing_counters();
+                    osd.set_show_counters(show);
+                }
+                Hotkey::Cheats => self.show_cheats(),
+                Hotkey::Menu => self.menu_requested = true,
+                Hotkey::Quit => self.running = false,
+            }
+        }
+
+        let status = if self.chip8.is_halted() {
+            Some("Halted".to_string())
+        } else if self.paused {
+            Some("Paused".to_string())
+        } else if self.media.keypad.is_turbo_held() {
+            Some(format!("Turbo x{}", self.effective_speed()))
+        } else {
+            None
+        };
+        self.media.display.osd.set_status(status);
+
+        let frames = match (self.paused, advance) {
+            (false, _) => self.effective_speed(),
+            (true, true) => 1,
+            (true, false) => 0,
+        };
+        if frames == 0 {
+            // don't leave the buzzer on while paused
+            self.media.sound.mute();
+        }
+        for _ in 0..frames {
+            if !self.step_frame() {
+                break;
+            }
+        }
+        self.media
+            .display
+            .osd
+            .count_frame(frames * self.chip8.cycles_per_frame());
+
+        if self.chip8.is_drawflag_set() {
+            self.media.display.set_screen(self.chip8.framebuffer());
+            self.chip8.clear_drawflag();
+        }
+        if self.media.display.needs_redraw() {
+            self.media.display.draw_screen();
+        }
+        self.wait_for_next_frame();
+    }
+
+    // one emulated 60Hz frame, with the buzzer following the sound timer. false when a fault
+    // stopped the machine, a break pauses it
+    fn step_frame(&mut self) -> bool {
+        if self.chip8.is_halted() {
+            return false;
+        }
+        if let Some(cheats) = &self.cheats {
+            cheats.apply(self.rom_hash, &mut self.chip8);
+        }
+        let result = self.run_frame();
+        if let Some(server) = &mut self.server {
+            server.end_frame(&self.chip8);
+        }
+        self.media
+            .sound
+            .set_pattern(self.chip8.audio_pattern().copied());
+        self.media.sound.set_pitch(self.chip8.audio_pitch());
+        if !self.chip8.is_halted() {
+            self.media.sound.set_active(self.chip8.sound_active());
+        }
+
+        let Err(error) = result else {
+            return true;
+        };
+        self.media.display.osd.show_message(&error.to_string());
+        if self.chip8.is_halted() {
+            self.media.sound.mute();
+        } else {
+            self.paused = true;
+        }
+        false
+    }
+
+    #[cfg(not(feature = "scripting"))]
+    fn run_frame(&mut self) -> Result<(), Chip8Error> {
+        self.chip8.run_frame()
+    }
+
+    #[cfg(feature = "scripting")]
+    fn run_frame(&mut self) -> Result<(), Chip8Error> {
+        let Some(script) = &mut self.script else {
+            return self.chip8.run_frame();
+        };
+        let result = script.run_frame(&mut self.chip8);
+        let osd = &mut self.media.display.osd;
+        if let Some(error) = script.error() {
+            eprintln!("script error: {}", error);
+            osd.show_message("Script error");
+            osd.set_overlay(Vec::new());
+            self.script = None;
+            return result;
+        }
+        osd.set_overlay(script.overlay());
+        if script.quit_requested() {
+            self.running = false;
+        }
+        result
+    }
+
+    // show the cheats for the running ROM over the screen until escape is pressed. selecting a
+    // freeze cheat switches it on or off, selecting a poke cheat writes it
+    fn show_cheats(&mut self) {
+        let hash = self.rom_hash;
+        let Some(cheats) = self
+            .cheats
+            .as_mut()
+            .map(|cheats| cheats.cheats_mut(hash))
+            .filter(|cheats| !cheats.is_empty())
+        else {
+            self.media
+                .display
+                .osd
+                .show_message("No cheats for this ROM");
+            return;
+        };
+
+        self.media.sound.mute();
+        let mut selected = 0;
+        let mut redraw = true;
+        loop {
+            if redraw {
+                let labels: Vec<String> = cheats.iter().map(cheat_label).collect();
+                self.media.display.draw_menu("Cheats", &labels, selected);
+                redraw = false;
+            }
+            for input in self.media.keypad.poll_menu() {
+                match input {
+                    MenuInput::Up => selected = selected.saturating_sub(1),
+                    MenuInput::Down => selected = (selected + 1).min(cheats.len() - 1),
+                    MenuInput::Select => {
+                        let cheat = &mut cheats[selected];
+                        match cheat.mode {
+                            Mode::Freeze => cheat.enabled = !cheat.enabled,
+                            Mode::Poke => {
+                                cheat.write(&mut self.chip8);
+                                self.media
+                                    .display
+                                    .osd
+                                    .show_message(&format!("Poked {}", cheat.name));
+                            }
+                        }
+                    }
+                    MenuInput::Quit => {
+                        // back to the game, with the screen the menu covered
+                        self.media.display.set_screen(self.chip8.framebuffer());
+                        self.media.keypad.clear_keyboard();
+                        return;
+                    }
+                }
+                redraw = true;
+            }
+            std::thread::sleep(Duration::from_millis(16));
+        }
+    }
+
+    fn wait_for_next_frame(&mut self) {
+        self.next_frame += FRAME_DURATION;
+        let now = Instant::now();
+        if self.next_frame > now {
+            std::thread::sleep(self.next_frame - now);
+        } else {
+            // running late, don't try to catch up
+            self.next_frame = now;
+        }
+    }
+
+    // ask for a host key for every chip8 key, in keypad order. each key is shown on screen using
+    // the built-in font. returns None if the user gives up by pressing escape or closing the window
+    pub fn bind_keys(&mut self) -> Option<Keymap> {
+        let mut keymap = self.media.keypad.keymap().clone();
+        keymap.clear_keys();
+        for key in drivers::KEYPAD_LAYOUT {
+            self.media
+                .display
+                .set_title(&format!("chip8 - press a key for {:X}", key));
+            self.media.display.clear_screen();
+            self.draw_key_glyph(key);
+            self.media.display.draw_screen();
+
+            let scancode = self.media.keypad.wait_for_scancode()?;
+            keymap.bind(scancode, key);
+        }
+        self.media.display.set_title("chip8");
+        self.media.display.clear_screen();
+        Some(keymap)
+    }
+
+    // draw the font sprite of a key, scaled 4 times, in the middle of the screen
+    fn draw_key_glyph(&mut self, key: usize) {
+        const SCALE: usize = 4;
+        let x_origin = (SCREEN_WIDTH - 4 * SCALE) / 2;
+        let y_origin = (SCREEN_HEIGHT - 5 * SCALE) / 2;
+        for (row, pixels) in FONTSET[key * 5..key * 5 + 5].iter().enumerate() {
+            for col in 0..4 {
+                if pixels & (0b1000_0000 >> col) == 0 {
+                    continue;
+                }
+                for dy in 0..SCALE {
+                    for dx in 0..SCALE {
+                        self.media.display.set_screen_pixel_state(
+                            x_origin + col * SCALE + dx,
+                            y_origin + row * SCALE + dy,
+                            true,
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+// a line of the cheat menu: a checkbox for freeze cheats, SET for pokes
+fn cheat_label(cheat: &Cheat) -> String {
+    let marker = match (cheat.mode, cheat.enabled) {
+        (Mode::Freeze, true) => "[X]",
+        (Mode::Freeze, false) => "[ ]",
+        (Mode::Poke, _) => "SET",
+    };
+    format!("{} {}", marker, cheat.name)
+}
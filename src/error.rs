@@ -0,0 +1,70 @@
+use std::fmt;
+
+// something the interpreter can't carry out, mostly faults of the running program
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Chip8Error {
+    // an opcode no supported platform defines
+    UnknownOpcode { address: u16, opcode: u16 },
+    // 0NNN, a call into COSMAC VIP machine code
+    MachineCode { address: u16, opcode: u16 },
+    // the program counter left the program area, or there is no room for a whole opcode
+    PcOutOfBounds { address: u16 },
+    // 2NNN with the call stack full
+    StackOverflow { address: u16 },
+    // 00EE with nothing to return to
+    StackUnderflow { address: u16 },
+    // a program too big for the memory above 0x200
+    RomTooLarge { size: usize },
+}
+
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Chip8Error::UnknownOpcode { address, opcode } => {
+                write!(f, "unknown opcode {:04X} at {:03X}", opcode, address)
+            }
+            Chip8Error::MachineCode { address, opcode } => {
+                write!(f, "machine code call {:04X} at {:03X}", opcode, address)
+            }
+            Chip8Error::PcOutOfBounds { address } => {
+                write!(f, "program counter out of bounds at {:03X}", address)
+            }
+            Chip8Error::StackOverflow { address } => {
+                write!(f, "stack overflow at {:03X}", address)
+            }
+            Chip8Error::StackUnderflow { address } => {
+                write!(f, "stack underflow at {:03X}", address)
+            }
+            Chip8Error::RomTooLarge { size } => {
+                write!(f, "ROM of {} bytes doesn't fit in memory", size)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Chip8Error {}
+
+// what to do when the program hits a Chip8Error
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FaultPolicy {
+    // skip the instruction like nothing happened
+    Ignore,
+    // skip the instruction and print a warning
+    Warn,
+    // stop the machine until it is reset
+    Halt,
+    // pause, so the state can be looked at and stepped with frame advance
+    Break,
+}
+
+impl FaultPolicy {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "ignore" => Some(FaultPolicy::Ignore),
+            "warn" => Some(FaultPolicy::Warn),
+            "halt" => Some(FaultPolicy::Halt),
+            "break" => Some(FaultPolicy::Break),
+            _ => None,
+        }
+    }
+}
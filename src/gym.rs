@@ -0,0 +1,393 @@
+// a Gym style environment for training agents on CHIP-8 games. the interpreter runs headless,
+// observations are the screen and rewards come from a per-ROM spec that says where the game
+// keeps its score. specs are text files, one setting per line:
+//
+//  # BRIX, the paddle moves with 4 and 6
+//  rom = aaa44d0b
+//  actions = none, 4, 6
+//  # the score, written by FX33 as three BCD digits
+//  reward = bcd[0x314]
+//  # 5 lives in VE, losing one costs 5 points
+//  reward = 5 * v[e]
+//  done = v[e] == 0
+//  done = v[5] == 96
+//  start_frames = 70
+//  frame_skip = 4
+//  sticky_actions = 0.25
+//  max_frames = 36000
+//
+// rom is the rom_hash the spec was written for. every action is a set of keys held together,
+// written with + between keys, none for no key. each reward line adds weight * change of a value
+// per step, done lines end the episode when any of them holds. values are mem[ADDR] (one byte),
+// bcd[ADDR] (three BCD digits, as stored by FX33) and v[X]. start_frames run with no keys after a
+// reset, to get past title screens and let the game set up its variables. with sticky actions,
+// every frame repeats the previous action with that probability instead of the chosen one
+use std::fs;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::{romdb, Chip8, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Value {
+    Memory(u16),
+    Bcd(u16),
+    Register(usize),
+}
+
+impl Value {
+    fn parse(text: &str) -> Option<Self> {
+        let (kind, rest) = text.trim().split_once('[')?;
+        let operand = rest.strip_suffix(']')?.trim();
+        match kind.trim() {
+            "mem" => parse_number(operand).map(|a| Value::Memory(a as u16 & 0xfff)),
+            "bcd" => parse_number(operand).map(|a| Value::Bcd(a as u16 & 0xfff)),
+            "v" => usize::from_str_radix(operand, 16)
+                .ok()
+                .filter(|x| *x < 16)
+                .map(Value::Register),
+            _ => None,
+        }
+    }
+
+    pub fn read(&self, chip8: &Chip8) -> i64 {
+        let memory = chip8.memory();
+        let byte = |address: u16| memory[address as usize & 0xfff] as i64;
+        match *self {
+            Value::Memory(address) => byte(address),
+            Value::Bcd(address) => byte(address) * 100 + byte(address + 1) * 10 + byte(address + 2),
+            Value::Register(x) => chip8.v()[x] as i64,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Condition {
+    pub value: Value,
+    pub comparison: Comparison,
+    pub operand: i64,
+}
+
+impl Condition {
+    fn parse(text: &str) -> Option<Self> {
+        // two character operators first so <= isn't read as <
+        const OPERATORS: [(&str, Comparison); 6] = [
+            ("==", Comparison::Equal),
+            ("!=", Comparison::NotEqual),
+            ("<=", Comparison::LessOrEqual),
+            (">=", Comparison::GreaterOrEqual),
+            ("<", Comparison::Less),
+            (">", Comparison::Greater),
+        ];
+        let (lhs, comparison, rhs) = OPERATORS.iter().find_map(|(op, comparison)| {
+            text.split_once(op)
+                .map(|(lhs, rhs)| (lhs, *comparison, rhs))
+        })?;
+        Some(Self {
+            value: Value::parse(lhs)?,
+            comparison,
+            operand: parse_number(rhs.trim())? as i64,
+        })
+    }
+
+    pub fn holds(&self, chip8: &Chip8) -> bool {
+        let value = self.value.read(chip8);
+        match self.comparison {
+            Comparison::Equal => value == self.operand,
+            Comparison::NotEqual => value != self.operand,
+            Comparison::Less => value < self.operand,
+            Comparison::LessOrEqual => value <= self.operand,
+            Comparison::Greater => value > self.operand,
+            Comparison::GreaterOrEqual => value >= self.operand,
+        }
+    }
+}
+
+// decimal, or hex with 0x
+fn parse_number(text: &str) -> Option<u64> {
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Spec {
+    pub rom_hash: Option<u32>,
+    // keys held for each action
+    pub actions: Vec<Vec<usize>>,
+    // weight and value, the reward is the weighted change of every value
+    pub rewards: Vec<(f64, Value)>,
+    pub done: Vec<Condition>,
+    pub start_frames: u32,
+    pub frame_skip: u32,
+    pub sticky_actions: f64,
+    // episodes are cut off after this many frames, 0 for no limit
+    pub max_frames: u64,
+}
+
+impl Default for Spec {
+    // every key on its own, nothing rewarded
+    fn default() -> Self {
+        Self {
+            rom_hash: None,
+            actions: std::iter::once(Vec::new())
+                .chain((0..16).map(|key| vec![key]))
+                .collect(),
+            rewards: Vec::new(),
+            done: Vec::new(),
+            start_frames: 0,
+            frame_skip: 4,
+            sticky_actions: 0.0,
+            max_frames: 0,
+        }
+    }
+}
+
+impl Spec {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text =
+            fs::read_to_string(path).map_err(|e| format!("can't read spec {}: {}", path, e))?;
+        Self::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut spec = Self::default();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let bad = |what: &str| format!("line {}: bad {} {}", n + 1, what, line);
+            let (key, value) = line
+                .split_once('=')
+                .ok_or(format!("line {}: expected `setting = value`", n + 1))?;
+            let (key, value) = (key.trim(), value.trim());
+            match key {
+                "rom" => {
+                    let hash = value.trim_start_matches("0x");
+                    spec.rom_hash = Some(u32::from_str_radix(hash, 16).map_err(|_| bad("hash"))?);
+                }
+                "actions" => {
+                    spec.actions = value
+                        .split(',')
+                        .map(|action| parse_action(action.trim()).ok_or_else(|| bad("action")))
+                        .collect::<Result<_, _>>()?;
+                }
+                "reward" => {
+                    let (weight, value) = match value.split_once('*') {
+                        Some((weight, value)) => {
+                            (weight.trim().parse().map_err(|_| bad("weight"))?, value)
+                        }
+                        None => (1.0, value),
+                    };
+                    spec.rewards
+                        .push((weight, Value::parse(value).ok_or_else(|| bad("value"))?));
+                }
+                "done" => spec
+                    .done
+                    .push(Condition::parse(value).ok_or_else(|| bad("condition"))?),
+                "start_frames" => spec.start_frames = value.parse().map_err(|_| bad("count"))?,
+                "frame_skip" => {
+                    spec.frame_skip = value
+                        .parse()
+                        .ok()
+                        .filter(|skip| *skip > 0)
+                        .ok_or_else(|| bad("frame skip"))?
+                }
+                "sticky_actions" => {
+                    spec.sticky_actions = value
+                        .parse()
+                        .ok()
+                        .filter(|p| (0.0..=1.0).contains(p))
+                        .ok_or_else(|| bad("probability"))?
+                }
+                "max_frames" => spec.max_frames = value.parse().map_err(|_| bad("count"))?,
+                _ => return Err(format!("line {}: unknown setting {}", n + 1, key)),
+            }
+        }
+        if spec.actions.is_empty() {
+            return Err("a spec needs at least one action".to_string());
+        }
+        Ok(spec)
+    }
+}
+
+// keys joined with +, or none
+fn parse_action(text: &str) -> Option<Vec<usize>> {
+    if text == "none" {
+        return Some(Vec::new());
+    }
+    text.split('+')
+        .map(|key| {
+            usize::from_str_radix(key.trim(), 16)
+                .ok()
+                .filter(|key| *key < 16)
+        })
+        .collect()
+}
+
+pub struct Environment {
+    chip8: Chip8,
+    spec: Spec,
+    rng: StdRng,
+    // action held on the last frame, repeated by sticky actions
+    last_action: usize,
+    // reward values as of the last step
+    values: Vec<i64>,
+    // frames since the last reset, not counting start_frames
+    frames: u64,
+}
+
+impl Environment {
+    // fails if the spec was written for a different ROM
+    pub fn new(rom: &[u8], spec: Spec) -> Result<Self, String> {
+        let hash = romdb::rom_hash(rom);
+        if let Some(expected) = spec.rom_hash.filter(|expected| *expected != hash) {
+            return Err(format!(
+                "the spec is for ROM {:08x}, this one is {:08x}",
+                expected, hash
+            ));
+        }
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(rom).map_err(|e| e.to_string())?;
+        let mut env = Self {
+            chip8,
+            spec,
+            rng: StdRng::from_entropy(),
+            last_action: 0,
+            values: Vec::new(),
+            frames: 0,
+        };
+        env.reset();
+        Ok(env)
+    }
+
+    // make episodes repeatable: sticky actions, and the game's own RND through the seed reset
+    // hands the interpreter. takes effect from the next reset
+    pub fn seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    pub fn spec(&self) -> &Spec {
+        &self.spec
+    }
+
+    pub fn set_frame_skip(&mut self, frames: u32) {
+        self.spec.frame_skip = frames.max(1);
+    }
+
+    pub fn set_sticky_actions(&mut self, probability: f64) {
+        self.spec.sticky_actions = probability.clamp(0.0, 1.0);
+    }
+
+    // actions are numbered from 0 to action_space() - 1
+    pub fn action_space(&self) -> usize {
+        self.spec.actions.len()
+    }
+
+    // the keys an action holds down, None for an action that doesn't exist
+    pub fn action_keys(&self, action: usize) -> Option<&[usize]> {
+        self.spec.actions.get(action).map(Vec::as_slice)
+    }
+
+    // height and width of an observation
+    pub fn observation_shape(&self) -> (usize, usize) {
+        (SCREEN_HEIGHT, SCREEN_WIDTH)
+    }
+
+    // the interpreter, e.g. to look at memory while writing a spec
+    pub fn chip8(&self) -> &Chip8 {
+        &self.chip8
+    }
+
+    // restart the game and return the first observation
+    pub fn reset(&mut self) -> Vec<u8> {
+        self.chip8.reset();
+        self.chip8.seed_rng(self.rng.gen());
+        for _ in 0..self.spec.start_frames {
+            let _ = self.chip8.run_frame();
+        }
+        self.last_action = 0;
+        self.frames = 0;
+        self.values = self.read_values();
+        self.observation()
+    }
+
+    // hold the keys of an action for frame_skip frames. returns the screen afterwards, the reward
+    // earned meanwhile and whether the episode is over
+    pub fn step(&mut self, action: usize) -> Result<(Vec<u8>, f64, bool), String> {
+        if action >= self.action_space() {
+            return Err(format!(
+                "no action {}, there are {}",
+                action,
+                self.action_space()
+            ));
+        }
+        let mut done = false;
+        for _ in 0..self.spec.frame_skip {
+            if self.spec.sticky_actions == 0.0 || !self.rng.gen_bool(self.spec.sticky_actions) {
+                self.last_action = action;
+            }
+            for key in 0..16 {
+                let pressed = self.spec.actions[self.last_action].contains(&key);
+                self.chip8.set_key(key, pressed);
+            }
+            let _ = self.chip8.run_frame();
+            self.frames += 1;
+            done = self.is_done();
+            if done {
+                break;
+            }
+        }
+
+        let values = self.read_values();
+        let reward = self
+            .spec
+            .rewards
+            .iter()
+            .zip(values.iter().zip(&self.values))
+            .map(|((weight, _), (now, before))| weight * (now - before) as f64)
+            .sum();
+        self.values = values;
+        Ok((self.observation(), reward, done))
+    }
+
+    fn is_done(&self) -> bool {
+        self.chip8.is_halted()
+            || (self.spec.max_frames > 0 && self.frames >= self.spec.max_frames)
+            || self
+                .spec
+                .done
+                .iter()
+                .any(|condition| condition.holds(&self.chip8))
+    }
+
+    fn read_values(&self) -> Vec<i64> {
+        self.spec
+            .rewards
+            .iter()
+            .map(|(_, value)| value.read(&self.chip8))
+            .collect()
+    }
+
+    // a byte per pixel, row by row, 1 when lit
+    fn observation(&self) -> Vec<u8> {
+        self.chip8
+            .framebuffer()
+            .iter()
+            .map(|lit| *lit as u8)
+            .collect()
+    }
+}
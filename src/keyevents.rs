@@ -0,0 +1,54 @@
+use std::collections::VecDeque;
+
+// edges are kept until the host takes them, past this the oldest ones are dropped
+pub const KEY_EVENT_QUEUE_LEN: usize = 64;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum KeyEvent {
+    Pressed(usize),
+    Released(usize),
+}
+
+// the press and release edges of the hex keypad in the order they happened. the host hands it the
+// whole keypad after every poll and takes the edges from the front
+#[derive(Default)]
+pub struct KeyEvents {
+    // key state as of the previous update, to find the keys that changed since
+    previous: [bool; 16],
+    queue: VecDeque<KeyEvent>,
+}
+
+impl KeyEvents {
+    pub fn update(&mut self, keys: &[bool; 16]) {
+        for (index, (was, is)) in self.previous.iter().zip(keys).enumerate() {
+            let event = match (was, is) {
+                (false, true) => KeyEvent::Pressed(index),
+                (true, false) => KeyEvent::Released(index),
+                _ => continue,
+            };
+            if self.queue.len() == KEY_EVENT_QUEUE_LEN {
+                self.queue.pop_front();
+            }
+            self.queue.push_back(event);
+        }
+        self.previous = *keys;
+    }
+
+    pub fn pop(&mut self) -> Option<KeyEvent> {
+        self.queue.pop_front()
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    // forget the keys and the edges not taken yet, e.g. when a new game starts
+    pub fn clear(&mut self) {
+        self.previous = [false; 16];
+        self.queue.clear();
+    }
+}
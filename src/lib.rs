@@ -1,8 +1,57 @@
-use rand::Rng;
-use std::fs::File;
-use std::io::Read;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 
+mod base64;
+#[cfg(feature = "sdl")]
+pub mod browser;
+#[cfg(feature = "capi")]
+pub mod capi;
+pub mod cheats;
+mod checksum;
+pub mod coverage;
+pub mod disasm;
+#[cfg(feature = "sdl")]
 mod drivers;
+#[cfg(feature = "sdl")]
+mod emulator;
+mod error;
+pub mod gym;
+mod json;
+mod keyevents;
+#[cfg(feature = "libretro")]
+pub mod libretro;
+pub mod opcodes;
+pub mod png;
+pub mod profiler;
+#[cfg(feature = "python")]
+pub mod python;
+mod quirks;
+pub mod ramsearch;
+pub mod romdb;
+#[cfg(feature = "scripting")]
+pub mod script;
+pub mod server;
+mod state;
+mod synth;
+#[cfg(feature = "tui")]
+pub mod tui;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod watch;
+
+#[cfg(feature = "sdl")]
+pub use drivers::{
+    Audio, Hotkey, Hotkeys, Keymap, KeymapConfig, Keypad, MenuInput, Osd, PadControl, PadInput,
+    WavWriter,
+};
+#[cfg(feature = "sdl")]
+pub use emulator::{Emulator, Media};
+pub use error::{Chip8Error, FaultPolicy};
+pub use keyevents::{KeyEvent, KeyEvents, KEY_EVENT_QUEUE_LEN};
+pub use quirks::Quirks;
+pub use synth::{
+    Buzzer, SamplePattern, SquareWave, Tone, Waveform, SAMPLES_PER_FRAME, SAMPLE_RATE,
+};
 
 pub const SCREEN_WIDTH: usize = 64;
 pub const SCREEN_HEIGHT: usize = 32;
@@ -10,6 +59,42 @@ pub const SCREEN_HEIGHT: usize = 32;
 const FONTSET_SIZE: usize = 0x50;
 const START_ADDRESS: usize = 0x200;
 
+// instructions executed per 60Hz frame when running at normal speed
+pub const CYCLES_PER_FRAME: u32 = 10;
+
+// the speed multiplier goes from 1 to MAX_SPEED frames per host frame, holding turbo multiplies
+// it again by TURBO_SPEED
+pub const MAX_SPEED: u32 = 8;
+pub const TURBO_SPEED: u32 = 4;
+
+// call stack depth of the COSMAC VIP interpreter, and of CHIP-48 and everything after it
+pub const VIP_STACK_DEPTH: usize = 12;
+pub const MAX_STACK_DEPTH: usize = 16;
+// the VIP keeps its stack in RAM just below 0xED0, growing down to 0xEA0
+const VIP_STACK_TOP: usize = 0xed0;
+
+// XO-CHIP pitch register value after a reset, plays audio patterns at 4000Hz
+const DEFAULT_PITCH: u8 = 64;
+
+const FONTSET: [u8; FONTSET_SIZE] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x20, 0x60, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+];
+
 struct Cpu {
     // 16-bit program counter
     pc: u16,
@@ -21,14 +106,20 @@ struct Cpu {
     sp: u16,
 
     v: [u8; 16],
+
+    // SCHIP RPL user flags, saved and restored by FX75 and FX85
+    rpl: [u8; 8],
+
+    // key that FX0A saw going down and is now waiting to be released
+    key_wait: Option<usize>,
 }
 
 struct Memory {
     // memory of the chip8. in truth this is too much. 4096 is enough for 12-bit addresses
     memory: [u8; 4096],
 
-    // stack to store 16 16-bit addresses
-    stack: [u16; 16],
+    // stack to store up to 16 16-bit addresses, unless the stack lives in RAM
+    stack: [u16; MAX_STACK_DEPTH],
 }
 
 // There are 2 timers in Chip8, both are 60Hz and once above 0, timers will decrement itself to 0
@@ -40,114 +131,567 @@ struct Timers {
     sound: u8,
 }
 
-pub struct Media {
-    pub sound: drivers::Audio, 
+impl Cpu {
+    fn new() -> Self {
+        Self {
+            pc: 0x0,
+            index: 0x0,
+            sp: 0x0,
+            v: [0x0; 16],
+            rpl: [0x0; 8],
+            key_wait: None,
+        }
+    }
+}
 
-    // display of the chip8 is 2048 pixels, each pixel can be either black or white
-    pub display: drivers::Video,
+impl Memory {
+    fn new() -> Self {
+        Self {
+            memory: [0x0; 4096],
+            stack: [0x0; MAX_STACK_DEPTH],
+        }
+    }
+}
 
-    // chip8 has a hex keypad (0x0 - 0xf)
-    pub keypad: drivers::Keypad,
+impl Timers {
+    fn new() -> Self {
+        Self {
+            delay: 0x0,
+            sound: 0x0,
+        }
+    }
+}
+
+// what a single step did
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Step {
+    // the instruction at address ran
+    Executed { address: u16, opcode: u16 },
+    // FX0A is waiting for a key, the same instruction runs again on the next step
+    WaitingForKey,
+    // the instruction faulted and the fault policy skipped it
+    Skipped(Chip8Error),
 }
 
+// the interpreter on its own: cpu, memory, timers, screen and keypad state. nothing here talks to
+// SDL, hosts feed it keys, run it and read the screen and buzzer back, see Emulator
 pub struct Chip8 {
     cpu: Cpu,
     memory: Memory,
     timers: Timers,
-    pub media: Media,
+
+    // display of the chip8 is 2048 pixels, each pixel can be either black or white
+    screen: [bool; SCREEN_WIDTH * SCREEN_HEIGHT],
+    // set when the screen changed, cleared by the host once it drew it
+    draw: bool,
+    // chip8 has a hex keypad (0x0 - 0xf)
+    keys: [bool; 16],
+    // keys as of the last timer tick, FX0A waits for a key that went down since
+    previous_keys: [bool; 16],
+    // true when the buzzer sounded on the last timer tick
+    buzzer: bool,
+    // XO-CHIP audio pattern loaded by F002, None until the program loads one
+    audio_pattern: Option<[u8; 16]>,
+    audio_pitch: u8,
+
+    // the loaded ROM, kept around for resets
+    rom: Vec<u8>,
+    cycles_per_frame: u32,
+
+    // what happens on unknown opcodes, machine code calls, a runaway program counter and stack
+    // faults
+    fault_policy: FaultPolicy,
+    stack_depth: usize,
+    // keep return addresses in memory at 0xEA0 like the VIP, so programs can see and clobber them
+    vip_stack: bool,
+    quirks: Quirks,
+    // set when a fault stopped the machine, cleared by a reset
+    halted: Option<Chip8Error>,
+    // the last fault warned about, so a fault hit in a loop is only reported once
+    last_warning: Option<Chip8Error>,
+    // for CXNN, seeded from the OS unless the host asks for a repeatable sequence
+    rng: StdRng,
+
+    profiler: Option<profiler::Profiler>,
+    coverage: Option<coverage::Coverage>,
+    // memory written by the last step, start and length
+    last_write: Option<(u16, usize)>,
+}
+
+impl Default for Chip8 {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Chip8 {
-    pub fn new(sdl_context: &sdl2::Sdl) -> Self {
-        Self {
-            cpu: Cpu {
-                pc: 0x0,
-                index: 0x0,
-                sp: 0x0,
-                v: [0x0; 16],
-            },
-            memory: Memory {
-                memory: [0x0; 4096],
-                stack: [0x0; 16],
-            },
-            timers: Timers {
-                delay: 0x0,
-                sound: 0x0,
-            },
-            media: Media {
-                sound: drivers::Audio::new(sdl_context), 
-                display: drivers::Video::new(sdl_context),
-                keypad: drivers::Keypad::new(sdl_context),
-            },
+    pub fn new() -> Self {
+        let mut chip8 = Self {
+            cpu: Cpu::new(),
+            memory: Memory::new(),
+            timers: Timers::new(),
+            screen: [false; SCREEN_WIDTH * SCREEN_HEIGHT],
+            draw: false,
+            keys: [false; 16],
+            previous_keys: [false; 16],
+            buzzer: false,
+            audio_pattern: None,
+            audio_pitch: DEFAULT_PITCH,
+            rom: Vec::new(),
+            cycles_per_frame: CYCLES_PER_FRAME,
+            fault_policy: FaultPolicy::Ignore,
+            stack_depth: MAX_STACK_DEPTH,
+            vip_stack: false,
+            quirks: Quirks::DEFAULT,
+            halted: None,
+            last_warning: None,
+            rng: StdRng::from_entropy(),
+            profiler: None,
+            coverage: None,
+            last_write: None,
+        };
+        chip8.reset();
+        chip8
+    }
+
+    // load a program to 0x200 and reset the machine
+    pub fn load_rom(&mut self, rom: &[u8]) -> Result<(), Chip8Error> {
+        if rom.len() > self.memory.memory.len() - START_ADDRESS {
+            return Err(Chip8Error::RomTooLarge { size: rom.len() });
         }
+        self.rom = rom.to_vec();
+        self.reset();
+        self.restart_reports();
+        Ok(())
     }
 
-    pub fn load_and_init(&mut self, filepath: &str) {
+    // soft reset: clear the cpu, memory, timers, screen and keypad and load the ROM again
+    pub fn reset(&mut self) {
+        self.cpu = Cpu::new();
+        self.memory = Memory::new();
+        self.timers = Timers::new();
+        self.screen = [false; SCREEN_WIDTH * SCREEN_HEIGHT];
+        self.draw = true;
+        self.keys = [false; 16];
+        self.previous_keys = [false; 16];
+        self.buzzer = false;
+        self.audio_pattern = None;
+        self.audio_pitch = DEFAULT_PITCH;
+        self.halted = None;
+        self.last_warning = None;
+
         // setup font
-        let chip8_font = [
-            0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
-            0x20, 0x60, 0x20, 0x20, 0x70, // 1
-            0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
-            0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
-            0x90, 0x90, 0xF0, 0x10, 0x10, // 4
-            0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
-            0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
-            0xF0, 0x10, 0x20, 0x40, 0x40, // 7
-            0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
-            0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
-            0xF0, 0x90, 0xF0, 0x90, 0x90, // A
-            0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
-            0xF0, 0x80, 0x80, 0x80, 0xF0, // C
-            0xE0, 0x90, 0x90, 0x90, 0xE0, // D
-            0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
-            0xF0, 0x80, 0xF0, 0x80, 0x80, // F
-        ];
-        self.memory.memory[..FONTSET_SIZE].copy_from_slice(&chip8_font);
-
-        // load the game to memory
-        let mut f = File::open(filepath).expect("ROM not found");
-        let mut buffer: Vec<u8> = Vec::new();
-        f.read_to_end(&mut buffer).unwrap();
-        self.memory.memory[START_ADDRESS..START_ADDRESS + buffer.len()].copy_from_slice(&buffer);
+        self.memory.memory[..FONTSET_SIZE].copy_from_slice(&FONTSET);
+        self.memory.memory[START_ADDRESS..START_ADDRESS + self.rom.len()]
+            .copy_from_slice(&self.rom);
         self.cpu.pc = START_ADDRESS as u16;
     }
 
-    pub fn emulate_cycle(&mut self) {
-        self.media.keypad.poll();
-        std::thread::sleep(std::time::Duration::from_micros(600));
-        let opcode = self.fetch_instr();
-        if let Some(opcode) = opcode {
-            self.execute_instr(opcode);
+    // swap in a new build of the ROM and restart it. keep_rpl carries the RPL flags over.
+    // keep_state puts the machine back the way it was just before the reload, except for the
+    // program itself which comes from the new ROM
+    pub fn reload_rom(
+        &mut self,
+        rom: &[u8],
+        keep_rpl: bool,
+        keep_state: bool,
+    ) -> Result<(), Chip8Error> {
+        if rom.len() > self.memory.memory.len() - START_ADDRESS {
+            return Err(Chip8Error::RomTooLarge { size: rom.len() });
+        }
+        let state = if keep_state {
+            Some(self.save_state())
+        } else {
+            None
+        };
+        let rpl = self.cpu.rpl;
+        let old_len = self.rom.len();
+
+        self.rom = rom.to_vec();
+        self.reset();
+        self.restart_reports();
+        if let Some(state) = state {
+            if self.load_state(&state).is_ok() {
+                // the old program may have been longer than the new one
+                let end = START_ADDRESS + old_len.max(self.rom.len());
+                self.memory.memory[START_ADDRESS..end].fill(0x0);
+                self.memory.memory[START_ADDRESS..START_ADDRESS + self.rom.len()]
+                    .copy_from_slice(&self.rom);
+            }
+        }
+        if keep_rpl {
+            self.cpu.rpl = rpl;
+        }
+        Ok(())
+    }
+
+    pub fn set_cycles_per_frame(&mut self, cycles: u32) {
+        self.cycles_per_frame = cycles.max(1);
+    }
+
+    pub fn cycles_per_frame(&self) -> u32 {
+        self.cycles_per_frame
+    }
+
+    pub fn set_fault_policy(&mut self, policy: FaultPolicy) {
+        self.fault_policy = policy;
+    }
+
+    // how many calls can be nested before 2NNN faults, at most MAX_STACK_DEPTH
+    pub fn set_stack_depth(&mut self, depth: usize) {
+        self.stack_depth = depth.clamp(1, MAX_STACK_DEPTH);
+    }
+
+    pub fn set_vip_stack(&mut self, vip_stack: bool) {
+        self.vip_stack = vip_stack;
+    }
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    // make CXNN repeatable: the same seed gives the same random numbers from here on
+    pub fn seed_rng(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    // true once a fault stopped the machine with FaultPolicy::Halt, until the next reset
+    pub fn is_halted(&self) -> bool {
+        self.halted.is_some()
+    }
+
+    // the fault that halted the machine
+    pub fn halt_reason(&self) -> Option<&Chip8Error> {
+        self.halted.as_ref()
+    }
+
+    // one emulated 60Hz frame: cycles_per_frame instructions and a timer tick. a halt or break
+    // ends the frame right at the faulting instruction and is returned
+    pub fn run_frame(&mut self) -> Result<(), Chip8Error> {
+        if let Some(error) = &self.halted {
+            return Err(error.clone());
+        }
+        let mut result = Ok(());
+        for _ in 0..self.cycles_per_frame {
+            if let Err(error) = self.step() {
+                result = Err(error);
+                break;
+            }
+        }
+        self.tick_timers();
+        result
+    }
+
+    // the 60Hz timer tick that ends a frame, for hosts that run the instructions with step
+    pub fn tick_timers(&mut self) {
+        if self.halted.is_none() {
+            self.update_timers();
+        }
+    }
+
+    // run one instruction. faults are handled according to the fault policy, the error is
+    // returned when the policy stops execution, and for every step tried while halted
+    pub fn step(&mut self) -> Result<Step, Chip8Error> {
+        if let Some(error) = &self.halted {
+            return Err(error.clone());
+        }
+        let address = self.cpu.pc;
+        self.last_write = None;
+        let result = match self.fetch_instr() {
+            Ok(opcode) => {
+                if let Some(coverage) = &mut self.coverage {
+                    coverage.mark_executed(address);
+                }
+                let result = self.execute_instr(opcode);
+                // a faulting call or return doesn't change the call stack, so it isn't profiled
+                if let (Ok(()), Some(profiler)) = (&result, &mut self.profiler) {
+                    profiler.record(address, opcode);
+                }
+                result.map(|()| {
+                    if opcode & 0xf0ff == 0xf00a && self.cpu.pc == address {
+                        Step::WaitingForKey
+                    } else {
+                        Step::Executed { address, opcode }
+                    }
+                })
+            }
+            Err(error) => Err(error),
+        };
+        match result {
+            Ok(step) => Ok(step),
+            Err(error) => self.fault(error),
         }
-        self.update_timers();
-        if self.is_drawflag_set() {
-            self.media.display.draw_screen();
+    }
+
+    fn fault(&mut self, error: Chip8Error) -> Result<Step, Chip8Error> {
+        match self.fault_policy {
+            FaultPolicy::Ignore => Ok(Step::Skipped(error)),
+            FaultPolicy::Warn => {
+                if self.last_warning.as_ref() != Some(&error) {
+                    eprintln!("warning: {}", error);
+                    self.last_warning = Some(error.clone());
+                }
+                Ok(Step::Skipped(error))
+            }
+            FaultPolicy::Halt => {
+                self.halted = Some(error.clone());
+                Err(error)
+            }
+            // the host decides how to break, the machine itself carries on when stepped again
+            FaultPolicy::Break => Err(error),
         }
     }
 
-    pub fn is_drawflag_set(&mut self) -> bool {
-        self.media.display.is_drawflag_set()
+    // the screen, row by row, true for lit pixels
+    pub fn framebuffer(&self) -> &[bool] {
+        &self.screen
     }
 
-    fn fetch_instr(&mut self) -> Option<u16> {
-        if self.cpu.pc >= 4096 || self.cpu.pc < 0x200 {
-            return None;
+    // the screen as one u64 per row, the leftmost pixel in the top bit
+    pub fn framebuffer_rows(&self) -> [u64; SCREEN_HEIGHT] {
+        let mut rows = [0; SCREEN_HEIGHT];
+        for (row, pixels) in rows.iter_mut().zip(self.screen.chunks(SCREEN_WIDTH)) {
+            for (x, pixel) in pixels.iter().enumerate() {
+                if *pixel {
+                    *row |= 1 << (SCREEN_WIDTH - 1 - x);
+                }
+            }
+        }
+        rows
+    }
+
+    // true when the screen changed since the last clear_drawflag
+    pub fn is_drawflag_set(&self) -> bool {
+        self.draw
+    }
+
+    pub fn clear_drawflag(&mut self) {
+        self.draw = false;
+    }
+
+    pub fn set_key(&mut self, key: usize, pressed: bool) {
+        self.keys[key & 0xf] = pressed;
+    }
+
+    pub fn is_key_pressed(&self, key: usize) -> bool {
+        self.keys[key & 0xf]
+    }
+
+    // whether the buzzer sounded on the last timer tick
+    pub fn sound_active(&self) -> bool {
+        self.buzzer
+    }
+
+    pub fn audio_pattern(&self) -> Option<&[u8; 16]> {
+        self.audio_pattern.as_ref()
+    }
+
+    pub fn audio_pitch(&self) -> u8 {
+        self.audio_pitch
+    }
+
+    pub fn pc(&self) -> u16 {
+        self.cpu.pc
+    }
+
+    pub fn index(&self) -> u16 {
+        self.cpu.index
+    }
+
+    pub fn v(&self) -> &[u8; 16] {
+        &self.cpu.v
+    }
+
+    pub fn rpl(&self) -> &[u8; 8] {
+        &self.cpu.rpl
+    }
+
+    pub fn sp(&self) -> u16 {
+        self.cpu.sp
+    }
+
+    // return addresses on the call stack, oldest first, wherever the stack lives
+    pub fn stack(&self) -> Vec<u16> {
+        (0..self.cpu.sp)
+            .map(|sp| {
+                if self.vip_stack {
+                    let slot = Self::vip_stack_slot(sp);
+                    u16::from_be_bytes([self.memory.memory[slot], self.memory.memory[slot + 1]])
+                } else {
+                    self.memory.stack[sp as usize]
+                }
+            })
+            .collect()
+    }
+
+    pub fn memory(&self) -> &[u8] {
+        &self.memory.memory
+    }
+
+    pub fn delay_timer(&self) -> u8 {
+        self.timers.delay
+    }
+
+    pub fn sound_timer(&self) -> u8 {
+        self.timers.sound
+    }
+
+    // poke the machine state from tools like the control server
+    pub fn set_pc(&mut self, pc: u16) {
+        self.cpu.pc = pc;
+    }
+
+    pub fn set_index(&mut self, index: u16) {
+        self.cpu.index = index;
+    }
+
+    pub fn set_v(&mut self, register: usize, value: u8) {
+        self.cpu.v[register & 0xf] = value;
+    }
+
+    pub fn set_delay_timer(&mut self, value: u8) {
+        self.timers.delay = value;
+    }
+
+    pub fn set_sound_timer(&mut self, value: u8) {
+        self.timers.sound = value;
+    }
+
+    pub fn memory_mut(&mut self) -> &mut [u8] {
+        &mut self.memory.memory
+    }
+
+    // start counting executed instructions, see profiler::Profiler
+    pub fn enable_profiler(&mut self) {
+        self.profiler = Some(profiler::Profiler::default());
+    }
+
+    pub fn profiler(&self) -> Option<&profiler::Profiler> {
+        self.profiler.as_ref()
+    }
+
+    // start recording how each byte of memory is used, see coverage::Coverage
+    pub fn enable_coverage(&mut self) {
+        self.coverage = Some(coverage::Coverage::default());
+    }
+
+    pub fn coverage(&self) -> Option<&coverage::Coverage> {
+        self.coverage.as_ref()
+    }
+
+    // a new ROM starts with an empty profile and coverage map, so the reports only cover the
+    // program they are written with
+    fn restart_reports(&mut self) {
+        if self.profiler.is_some() {
+            self.enable_profiler();
+        }
+        if self.coverage.is_some() {
+            self.enable_coverage();
+        }
+    }
+
+    // the memory the last step wrote to with FX33, FX55 or a CALL on the VIP stack, as start
+    // address and length
+    pub fn last_write(&self) -> Option<(u16, usize)> {
+        self.last_write
+    }
+
+    // the ROM as loaded, before the program modified anything
+    pub fn rom(&self) -> &[u8] {
+        &self.rom
+    }
+
+    // the address offset bytes past I. memory is 4 KiB and I can point past its end after FX1E or
+    // a poke, so accesses wrap around like the ones from F002
+    fn indexed(&self, offset: usize) -> usize {
+        (self.cpu.index as usize + offset) & 0xfff
+    }
+
+    fn mark_read(&mut self, address: u16, len: usize) {
+        if let Some(coverage) = &mut self.coverage {
+            coverage.mark_read(address, len);
+        }
+    }
+
+    fn mark_written(&mut self, address: u16, len: usize) {
+        self.last_write = Some((address, len));
+        if let Some(coverage) = &mut self.coverage {
+            coverage.mark_written(address, len);
+        }
+    }
+
+    fn fetch_instr(&mut self) -> Result<u16, Chip8Error> {
+        // the second byte of the opcode has to be in memory too
+        if self.cpu.pc as usize + 1 >= self.memory.memory.len()
+            || (self.cpu.pc as usize) < START_ADDRESS
+        {
+            return Err(Chip8Error::PcOutOfBounds {
+                address: self.cpu.pc,
+            });
         }
         let mut opcode: u16 = (self.memory.memory[self.cpu.pc as usize] as u16) << 8;
         opcode |= self.memory.memory[(self.cpu.pc + 1) as usize] as u16;
         self.cpu.pc += 2;
-        Some(opcode)
+        Ok(opcode)
+    }
+
+    // where the VIP stack keeps the entry at the given stack pointer
+    fn vip_stack_slot(sp: u16) -> usize {
+        VIP_STACK_TOP - 2 * (sp as usize + 1)
+    }
+
+    fn push(&mut self, return_address: u16, address: u16) -> Result<(), Chip8Error> {
+        if self.cpu.sp as usize >= self.stack_depth {
+            return Err(Chip8Error::StackOverflow { address });
+        }
+        if self.vip_stack {
+            let slot = Self::vip_stack_slot(self.cpu.sp);
+            self.memory.memory[slot..slot + 2].copy_from_slice(&return_address.to_be_bytes());
+            self.mark_written(slot as u16, 2);
+        } else {
+            self.memory.stack[self.cpu.sp as usize] = return_address;
+        }
+        self.cpu.sp += 1;
+        Ok(())
+    }
+
+    fn pop(&mut self, address: u16) -> Result<u16, Chip8Error> {
+        if self.cpu.sp == 0 {
+            return Err(Chip8Error::StackUnderflow { address });
+        }
+        self.cpu.sp -= 1;
+        if self.vip_stack {
+            let slot = Self::vip_stack_slot(self.cpu.sp);
+            self.mark_read(slot as u16, 2);
+            let bytes = [self.memory.memory[slot], self.memory.memory[slot + 1]];
+            Ok(u16::from_be_bytes(bytes))
+        } else {
+            Ok(self.memory.stack[self.cpu.sp as usize])
+        }
     }
 
-    fn execute_instr(&mut self, opcode: u16) {
+    fn execute_instr(&mut self, opcode: u16) -> Result<(), Chip8Error> {
+        // the opcode was already fetched, so it starts 2 bytes back
+        let address = self.cpu.pc - 2;
+        match opcodes::class(opcode) {
+            opcodes::Class::Unknown => return Err(Chip8Error::UnknownOpcode { address, opcode }),
+            opcodes::Class::Sys => return Err(Chip8Error::MachineCode { address, opcode }),
+            _ => {}
+        }
         match opcode & 0xf000 {
             0x0000 => {
                 match opcode & 0xf0ff {
-                    0x00e0 => self.media.display.clear_screen(),
+                    0x00e0 => {
+                        self.screen = [false; SCREEN_WIDTH * SCREEN_HEIGHT];
+                        self.draw = true;
+                    }
                     0x00ee => {
                         // return from subroutine
-                        self.cpu.sp -= 1;
-                        self.cpu.pc = self.memory.stack[(self.cpu.sp) as usize];
+                        self.cpu.pc = self.pop(address)?;
                     }
                     _ => {}
                 }
@@ -158,29 +702,23 @@ impl Chip8 {
             }
             0x2000 => {
                 // call subroutine
-                self.memory.stack[(self.cpu.sp) as usize] = self.cpu.pc;
-                self.cpu.sp += 1;
+                self.push(self.cpu.pc, address)?;
                 self.cpu.pc = opcode & 0x0fff;
             }
-            0x3000 => {
-                // skip next instruction if Vx == NN
-                if self.cpu.v[((opcode & 0x0f00) >> 8) as usize] == (opcode & 0x00ff) as u8 {
-                    self.cpu.pc += 2;
-                }
+            // skip next instruction if Vx == NN
+            0x3000 if self.cpu.v[((opcode & 0x0f00) >> 8) as usize] == (opcode & 0x00ff) as u8 => {
+                self.cpu.pc += 2;
             }
-            0x4000 => {
-                // skip next instruction if Vx != NN
-                if self.cpu.v[((opcode & 0x0f00) >> 8) as usize] != (opcode & 0x00ff) as u8 {
-                    self.cpu.pc += 2;
-                }
+            // skip next instruction if Vx != NN
+            0x4000 if self.cpu.v[((opcode & 0x0f00) >> 8) as usize] != (opcode & 0x00ff) as u8 => {
+                self.cpu.pc += 2;
             }
-            0x5000 => {
-                // skip next instruction if Vx == Vy
+            // skip next instruction if Vx == Vy
+            0x5000
                 if self.cpu.v[((opcode & 0x0f00) >> 8) as usize]
-                    == self.cpu.v[((opcode & 0x00f0) >> 4) as usize]
-                {
-                    self.cpu.pc += 2;
-                }
+                    == self.cpu.v[((opcode & 0x00f0) >> 4) as usize] =>
+            {
+                self.cpu.pc += 2;
             }
             0x6000 => {
                 // set Vx to NN
@@ -202,17 +740,26 @@ impl Chip8 {
                     0x8001 => {
                         // set Vx |= Vy
                         self.cpu.v[((opcode & 0x0f00) >> 8) as usize] |=
-                            self.cpu.v[((opcode & 0x00f0) >> 4) as usize]
+                            self.cpu.v[((opcode & 0x00f0) >> 4) as usize];
+                        if self.quirks.vf_reset {
+                            self.cpu.v[0xf] = 0;
+                        }
                     }
                     0x8002 => {
                         // set Vx &= Vy
                         self.cpu.v[((opcode & 0x0f00) >> 8) as usize] &=
-                            self.cpu.v[((opcode & 0x00f0) >> 4) as usize]
+                            self.cpu.v[((opcode & 0x00f0) >> 4) as usize];
+                        if self.quirks.vf_reset {
+                            self.cpu.v[0xf] = 0;
+                        }
                     }
                     0x8003 => {
                         // set Vx ^= Vy
                         self.cpu.v[((opcode & 0x0f00) >> 8) as usize] ^=
-                            self.cpu.v[((opcode & 0x00f0) >> 4) as usize]
+                            self.cpu.v[((opcode & 0x00f0) >> 4) as usize];
+                        if self.quirks.vf_reset {
+                            self.cpu.v[0xf] = 0;
+                        }
                     }
                     0x8004 => {
                         // Vx = Vx + Vy ; if carry then v[f] = 1; else v[f] = 0;
@@ -226,59 +773,69 @@ impl Chip8 {
                         self.cpu.v[((opcode & 0x0f00) >> 8) as usize] = result;
                     }
                     0x8005 => {
-                        // Vx = Vx - Vy ; if borrow then v[f] = 1; else v[f] = 0;
+                        // Vx = Vx - Vy ; if borrow then v[f] = 0; else v[f] = 1;
                         let (result, borrow) = self.cpu.v[((opcode & 0x0f00) >> 8) as usize]
                             .overflowing_sub(self.cpu.v[((opcode & 0x00f0) >> 4) as usize]);
-                        if borrow {
-                            self.cpu.v[0xf] = 1;
-                        } else {
-                            self.cpu.v[0xf] = 0;
-                        }
+                        self.cpu.v[0xf] = !borrow as u8;
                         self.cpu.v[((opcode & 0x0f00) >> 8) as usize] = result;
                     }
                     0x8006 => {
-                        // set Vx >>= 1
-                        self.cpu.v[0xf] = self.cpu.v[((opcode & 0x0f00) >> 8) as usize] & 1;
-                        self.cpu.v[((opcode & 0x0f00) >> 8) as usize] >>= 1;
+                        // set Vx >>= 1, or Vx = Vy >> 1 with the shift quirk
+                        let x = ((opcode & 0x0f00) >> 8) as usize;
+                        let y = if self.quirks.shift_vy {
+                            ((opcode & 0x00f0) >> 4) as usize
+                        } else {
+                            x
+                        };
+                        let value = self.cpu.v[y];
+                        self.cpu.v[0xf] = value & 1;
+                        self.cpu.v[x] = value >> 1;
                     }
                     0x8007 => {
-                        // set Vx = Vy - Vx; if carry v[f] = 1; else v[f] = 0
+                        // set Vx = Vy - Vx; if borrow v[f] = 0; else v[f] = 1
                         let (result, borrow) = self.cpu.v[((opcode & 0x00f0) >> 4) as usize]
                             .overflowing_sub(self.cpu.v[((opcode & 0x0f00) >> 8) as usize]);
-                        if borrow {
-                            self.cpu.v[0xf] = 1;
-                        } else {
-                            self.cpu.v[0xf] = 0;
-                        }
+                        self.cpu.v[0xf] = !borrow as u8;
                         self.cpu.v[((opcode & 0x0f00) >> 8) as usize] = result;
                     }
                     0x800e => {
-                        // set Vx <<= 1
-                        self.cpu.v[0xf] = (self.cpu.v[((opcode & 0x0f00) >> 8) as usize] >> 7) & 1;
-                        self.cpu.v[((opcode & 0x0f00) >> 8) as usize] <<= 1;
+                        // set Vx <<= 1, or Vx = Vy << 1 with the shift quirk
+                        let x = ((opcode & 0x0f00) >> 8) as usize;
+                        let y = if self.quirks.shift_vy {
+                            ((opcode & 0x00f0) >> 4) as usize
+                        } else {
+                            x
+                        };
+                        let value = self.cpu.v[y];
+                        self.cpu.v[0xf] = (value >> 7) & 1;
+                        self.cpu.v[x] = value << 1;
                     }
                     _ => {}
                 }
             }
-            0x9000 => {
-                // if (Vx != Vy) then pc++
+            // if (Vx != Vy) then pc++
+            0x9000
                 if self.cpu.v[((opcode & 0x0f00) >> 8) as usize]
-                    != self.cpu.v[((opcode & 0x00f0) >> 4) as usize]
-                {
-                    self.cpu.pc += 2;
-                }
+                    != self.cpu.v[((opcode & 0x00f0) >> 4) as usize] =>
+            {
+                self.cpu.pc += 2;
             }
             0xa000 => {
                 // cpu.i = 0x0NNN
                 self.cpu.index = opcode & 0x0fff;
             }
             0xb000 => {
-                // unconditional jump
-                self.cpu.pc = (self.cpu.v[0] as u16) + (opcode & 0x0fff);
+                // unconditional jump to NNN + V0, or XNN + VX with the jump quirk
+                let x = if self.quirks.jump_vx {
+                    ((opcode & 0x0f00) >> 8) as usize
+                } else {
+                    0
+                };
+                self.cpu.pc = (self.cpu.v[x] as u16) + (opcode & 0x0fff);
             }
             0xc000 => {
                 // generate random number and and it with nn
-                let rng: u8 = rand::thread_rng().gen();
+                let rng: u8 = self.rng.gen();
                 self.cpu.v[((opcode & 0x0f00) >> 8) as usize] = rng & ((opcode & 0x00ff) as u8);
             }
             0xd000 => {
@@ -290,45 +847,40 @@ impl Chip8 {
                 let x_coord = self.cpu.v[((opcode & 0x0f00) >> 8) as usize] as u16;
                 let y_coord = self.cpu.v[((opcode & 0x00f0) >> 4) as usize] as u16;
                 let height = opcode & 0x000f;
+                self.mark_read(self.indexed(0) as u16, height as usize);
                 self.cpu.v[0xf] = 0;
                 // loop through each row of the sprite
                 for yline in 0..height {
                     // sprite that should be drawn is in row by row at address specified by index
                     // register
-                    let pixels = self.memory.memory[(self.cpu.index + yline as u16) as usize];
+                    let pixels = self.memory.memory[self.indexed(yline as usize)];
                     for xline in 0..8 {
                         // if the bit is in memory and corresponding pixel is not 0, we set v[0xf]
                         // = 1
                         if (pixels & (0b1000_0000 >> xline)) != 0 {
                             let x = (x_coord + xline) as usize % SCREEN_WIDTH;
                             let y = (y_coord + yline) as usize % SCREEN_HEIGHT;
-                            if self
-                                .media
-                                .display
-                                .get_screen_pixel_state(x as usize, y as usize)
-                            {
+                            if self.get_screen_pixel_state(x, y) {
                                 self.cpu.v[0xf] = 1;
                             }
-                            self.media
-                                .display
-                                .set_screen_pixel_state(x as usize, y as usize, true)
+                            self.set_screen_pixel_state(x, y, true)
                         }
                     }
                 }
-                self.media.display.set_drawflag(true);
+                self.draw = true;
             }
             0xe000 => match opcode & 0xf0ff {
                 0xe09e => {
                     // skip instruction if key index in Vx is pressed
-                    let key_index = self.cpu.v[((opcode & 0x0f00) >> 8) as usize];
-                    if self.media.keypad.is_key_pressed(key_index as usize) {
+                    let key_index = self.cpu.v[((opcode & 0x0f00) >> 8) as usize] & 0xf;
+                    if self.keys[key_index as usize] {
                         self.cpu.pc += 2
                     }
                 }
                 0xe0a1 => {
                     // skip instruction if key index in Vx is not pressed
-                    let key_index = self.cpu.v[((opcode & 0x0f00) >> 8) as usize];
-                    if !self.media.keypad.is_key_pressed(key_index as usize) {
+                    let key_index = self.cpu.v[((opcode & 0x0f00) >> 8) as usize] & 0xf;
+                    if !self.keys[key_index as usize] {
                         self.cpu.pc += 2
                     }
                 }
@@ -342,23 +894,44 @@ impl Chip8 {
                     }
                     0xf00a => {
                         // wait for key press
-                        // block until key is pressed. if key is pressed, store it in Vx, if
-                        // multiple keys are pressed, save the one with lowest index.
+                        // like the COSMAC VIP, block until a key goes down and then until that
+                        // key is released, and only then store it in Vx. if multiple keys go
+                        // down at once, take the one with lowest index.
                         // we can t use a rust loop or any kind of infinity looops because we want
-                        // the cpu to execute key pressed opcode in case of a key press and update
-                        // keypad states
-                        let mut key_pressed = false;
-                        for key_index in 0..self.media.keypad.get_keypad_len() {
-                            if self.media.keypad.is_key_pressed(key_index) {
-                                self.cpu.v[((opcode & 0x0f00) >> 8) as usize] = key_index as u8;
-                                key_pressed = true;
-                                break;
+                        // the keypad to be polled between each try, so the instruction is
+                        // executed again until the key is released
+                        match self.cpu.key_wait {
+                            None => {
+                                for key_index in 0..self.keys.len() {
+                                    if self.keys[key_index] && !self.previous_keys[key_index] {
+                                        self.cpu.key_wait = Some(key_index);
+                                        break;
+                                    }
+                                }
+                                self.cpu.pc -= 2;
+                            }
+                            Some(key_index) => {
+                                if self.keys[key_index] {
+                                    self.cpu.pc -= 2;
+                                } else {
+                                    self.cpu.v[((opcode & 0x0f00) >> 8) as usize] = key_index as u8;
+                                    self.cpu.key_wait = None;
+                                }
                             }
                         }
-                        if !key_pressed {
-                            // if key is not pressed, execute this in
-                            self.cpu.pc -= 2;
+                    }
+                    0xf002 if opcode == 0xf002 => {
+                        // XO-CHIP: load the 16 byte audio pattern from memory at index register
+                        let mut pattern = [0x0; 16];
+                        self.mark_read(self.indexed(0) as u16, pattern.len());
+                        for (i, byte) in pattern.iter_mut().enumerate() {
+                            *byte = self.memory.memory[self.indexed(i)];
                         }
+                        self.audio_pattern = Some(pattern);
+                    }
+                    0xf03a => {
+                        // XO-CHIP: set the audio pitch register to Vx
+                        self.audio_pitch = self.cpu.v[((opcode & 0x0f00) >> 8) as usize];
                     }
                     0xf015 => {
                         self.timers.delay = self.cpu.v[((opcode & 0x0f00) >> 8) as usize];
@@ -383,41 +956,69 @@ impl Chip8 {
                         let of_tens = ((value / 10.0) % 10.0) as u8;
                         let of_ones = (value % 10.0) as u8;
 
-                        self.memory.memory[self.cpu.index as usize] = of_hundreds;
-                        self.memory.memory[(self.cpu.index + 1) as usize] = of_tens;
-                        self.memory.memory[(self.cpu.index + 2) as usize] = of_ones;
+                        self.memory.memory[self.indexed(0)] = of_hundreds;
+                        self.memory.memory[self.indexed(1)] = of_tens;
+                        self.memory.memory[self.indexed(2)] = of_ones;
+                        self.mark_written(self.indexed(0) as u16, 3);
                     }
                     0xf055 => {
                         // store regs until n in memory start by address in index register
                         let n = ((opcode & 0x0f00) >> 8) as usize;
                         for i in 0..=n {
-                            self.memory.memory[self.cpu.index as usize + i] = self.cpu.v[i];
+                            self.memory.memory[self.indexed(i)] = self.cpu.v[i];
+                        }
+                        self.mark_written(self.indexed(0) as u16, n + 1);
+                        if self.quirks.load_increments_i {
+                            self.cpu.index = self.cpu.index.wrapping_add(n as u16 + 1);
                         }
                     }
                     0xf065 => {
                         // load to regs until n from memory start by address in index register
                         let n = ((opcode & 0x0f00) >> 8) as usize;
                         for i in 0..=n {
-                            self.cpu.v[i] = self.memory.memory[self.cpu.index as usize + i];
+                            self.cpu.v[i] = self.memory.memory[self.indexed(i)];
+                        }
+                        self.mark_read(self.indexed(0) as u16, n + 1);
+                        if self.quirks.load_increments_i {
+                            self.cpu.index = self.cpu.index.wrapping_add(n as u16 + 1);
                         }
                     }
+                    0xf075 => {
+                        // SCHIP: store regs until n in the RPL flags, n is at most 7
+                        let n = (((opcode & 0x0f00) >> 8) as usize).min(7);
+                        self.cpu.rpl[..=n].copy_from_slice(&self.cpu.v[..=n]);
+                    }
+                    0xf085 => {
+                        // SCHIP: load regs until n from the RPL flags
+                        let n = (((opcode & 0x0f00) >> 8) as usize).min(7);
+                        self.cpu.v[..=n].copy_from_slice(&self.cpu.rpl[..=n]);
+                    }
                     _ => {}
                 }
             }
             _ => {}
         }
+        Ok(())
     }
 
     fn update_timers(&mut self) {
         if self.timers.delay > 0 {
             self.timers.delay -= 1;
         }
+        // the buzzer sounds for as long as the sound timer is non-zero
+        self.buzzer = self.timers.sound > 0;
         if self.timers.sound > 0 {
-            if self.timers.sound == 1 {
-                self.media.sound.beep();
-            }
-            self.media.sound.beep_stop();
             self.timers.sound -= 1;
         }
+        // FX0A only takes keys that went down after this
+        self.previous_keys = self.keys;
+    }
+
+    fn get_screen_pixel_state(&self, x: usize, y: usize) -> bool {
+        self.screen[(x % SCREEN_WIDTH) + SCREEN_WIDTH * (y % SCREEN_HEIGHT)]
+    }
+
+    fn set_screen_pixel_state(&mut self, x: usize, y: usize, state: bool) {
+        self.screen[(x % SCREEN_WIDTH) + SCREEN_WIDTH * (y % SCREEN_HEIGHT)] ^= state;
     }
 }
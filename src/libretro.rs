@@ -0,0 +1,522 @@
+// the interpreter as a libretro core. build it with
+// cargo rustc --lib --release --no-default-features --features libretro --crate-type cdylib
+// and load target/release/libchip8.so in a libretro frontend
+use std::ffi::{c_char, c_uint, c_void, CStr};
+use std::sync::Mutex;
+
+use crate::{
+    Buzzer, Chip8, FaultPolicy, Quirks, Tone, MAX_STACK_DEPTH, SAMPLES_PER_FRAME, SAMPLE_RATE,
+    SCREEN_HEIGHT, SCREEN_WIDTH, VIP_STACK_DEPTH,
+};
+
+const RETRO_API_VERSION: c_uint = 1;
+
+const RETRO_ENVIRONMENT_SET_PIXEL_FORMAT: c_uint = 10;
+const RETRO_ENVIRONMENT_SET_INPUT_DESCRIPTORS: c_uint = 11;
+const RETRO_ENVIRONMENT_GET_VARIABLE: c_uint = 15;
+const RETRO_ENVIRONMENT_SET_VARIABLES: c_uint = 16;
+const RETRO_ENVIRONMENT_GET_VARIABLE_UPDATE: c_uint = 17;
+const RETRO_PIXEL_FORMAT_XRGB8888: c_uint = 1;
+
+const RETRO_DEVICE_JOYPAD: c_uint = 1;
+const RETRO_DEVICE_KEYBOARD: c_uint = 3;
+const RETRO_REGION_NTSC: c_uint = 0;
+
+// lit and unlit pixels, the same green as the SDL window
+const PIXEL_ON: u32 = 0x0000_ff00;
+const PIXEL_OFF: u32 = 0x0000_0000;
+
+// joypad button ids to chip8 keys. the d-pad is on 2/4/6/8 and A on 5 like most CHIP-8 games
+// expect, every other key gets a button of its own
+const JOYPAD_KEYS: [(c_uint, usize, &CStr); 16] = [
+    (4, 0x2, c"Up (2)"),
+    (5, 0x8, c"Down (8)"),
+    (6, 0x4, c"Left (4)"),
+    (7, 0x6, c"Right (6)"),
+    (8, 0x5, c"A (5)"),
+    (0, 0x0, c"B (0)"),
+    (9, 0x1, c"X (1)"),
+    (1, 0x3, c"Y (3)"),
+    (10, 0x7, c"L (7)"),
+    (11, 0x9, c"R (9)"),
+    (12, 0xa, c"L2 (A)"),
+    (13, 0xb, c"R2 (B)"),
+    (2, 0xc, c"Select (C)"),
+    (3, 0xd, c"Start (D)"),
+    (14, 0xe, c"L3 (E)"),
+    (15, 0xf, c"R3 (F)"),
+];
+
+// libretro keyboard ids are ascii for letters and digits. the left of a qwerty keyboard
+const KEYBOARD_KEYS: [(u8, usize); 16] = [
+    (b'1', 0x1),
+    (b'2', 0x2),
+    (b'3', 0x3),
+    (b'4', 0xc),
+    (b'q', 0x4),
+    (b'w', 0x5),
+    (b'e', 0x6),
+    (b'r', 0xd),
+    (b'a', 0x7),
+    (b's', 0x8),
+    (b'd', 0x9),
+    (b'f', 0xe),
+    (b'z', 0xa),
+    (b'x', 0x0),
+    (b'c', 0xb),
+    (b'v', 0xf),
+];
+
+// core options, the first value is the default
+const PLATFORM_KEY: &CStr = c"chip8_platform";
+const CYCLES_KEY: &CStr = c"chip8_cycles";
+const FAULT_KEY: &CStr = c"chip8_on_fault";
+const QUIRKS_KEY: &CStr = c"chip8_quirks";
+const VARIABLES: [RetroVariable; 5] = [
+    RetroVariable {
+        key: PLATFORM_KEY.as_ptr(),
+        value: c"Platform; CHIP-48|COSMAC VIP".as_ptr(),
+    },
+    RetroVariable {
+        key: CYCLES_KEY.as_ptr(),
+        value: c"Instructions per frame; 10|7|15|20|30|50|100".as_ptr(),
+    },
+    RetroVariable {
+        key: FAULT_KEY.as_ptr(),
+        value: c"On invalid opcodes; ignore|halt".as_ptr(),
+    },
+    RetroVariable {
+        key: QUIRKS_KEY.as_ptr(),
+        value: c"Shift, load/store, jump and VF reset quirks; default|chip8|schip|xochip".as_ptr(),
+    },
+    RetroVariable {
+        key: std::ptr::null(),
+        value: std::ptr::null(),
+    },
+];
+
+#[repr(C)]
+pub struct RetroSystemInfo {
+    library_name: *const c_char,
+    library_version: *const c_char,
+    valid_extensions: *const c_char,
+    need_fullpath: bool,
+    block_extract: bool,
+}
+
+#[repr(C)]
+pub struct RetroGameGeometry {
+    base_width: c_uint,
+    base_height: c_uint,
+    max_width: c_uint,
+    max_height: c_uint,
+    aspect_ratio: f32,
+}
+
+#[repr(C)]
+pub struct RetroSystemTiming {
+    fps: f64,
+    sample_rate: f64,
+}
+
+#[repr(C)]
+pub struct RetroSystemAvInfo {
+    geometry: RetroGameGeometry,
+    timing: RetroSystemTiming,
+}
+
+#[repr(C)]
+pub struct RetroGameInfo {
+    pub path: *const c_char,
+    pub data: *const c_void,
+    pub size: usize,
+    pub meta: *const c_char,
+}
+
+#[repr(C)]
+struct RetroVariable {
+    key: *const c_char,
+    value: *const c_char,
+}
+
+#[repr(C)]
+struct RetroInputDescriptor {
+    port: c_uint,
+    device: c_uint,
+    index: c_uint,
+    id: c_uint,
+    description: *const c_char,
+}
+
+type EnvironmentFn = unsafe extern "C" fn(cmd: c_uint, data: *mut c_void) -> bool;
+type VideoRefreshFn =
+    unsafe extern "C" fn(data: *const c_void, width: c_uint, height: c_uint, pitch: usize);
+type AudioSampleFn = unsafe extern "C" fn(left: i16, right: i16);
+type AudioSampleBatchFn = unsafe extern "C" fn(data: *const i16, frames: usize) -> usize;
+type InputPollFn = unsafe extern "C" fn();
+type InputStateFn =
+    unsafe extern "C" fn(port: c_uint, device: c_uint, index: c_uint, id: c_uint) -> i16;
+
+struct Core {
+    chip8: Chip8,
+    buzzer: Buzzer,
+    samples: Vec<f32>,
+    video: Vec<u32>,
+    environment: Option<EnvironmentFn>,
+    video_refresh: Option<VideoRefreshFn>,
+    audio_batch: Option<AudioSampleBatchFn>,
+    input_poll: Option<InputPollFn>,
+    input_state: Option<InputStateFn>,
+}
+
+// libretro is a C API around a single instance
+static CORE: Mutex<Option<Core>> = Mutex::new(None);
+
+fn with_core<T>(f: impl FnOnce(&mut Core) -> T) -> Option<T> {
+    let mut core = CORE.lock().unwrap_or_else(|e| e.into_inner());
+    core.as_mut().map(f)
+}
+
+impl Core {
+    fn new() -> Self {
+        Self {
+            chip8: Chip8::new(),
+            buzzer: Buzzer::new(Tone::default(), SAMPLE_RATE),
+            samples: vec![0.0; SAMPLES_PER_FRAME],
+            video: vec![PIXEL_OFF; SCREEN_WIDTH * SCREEN_HEIGHT],
+            environment: None,
+            video_refresh: None,
+            audio_batch: None,
+            input_poll: None,
+            input_state: None,
+        }
+    }
+
+    fn environment(&self, cmd: c_uint, data: *mut c_void) -> bool {
+        match self.environment {
+            Some(environment) => unsafe { environment(cmd, data) },
+            None => false,
+        }
+    }
+
+    fn variable(&self, key: &CStr) -> Option<String> {
+        let mut variable = RetroVariable {
+            key: key.as_ptr(),
+            value: std::ptr::null(),
+        };
+        let found = self.environment(
+            RETRO_ENVIRONMENT_GET_VARIABLE,
+            &mut variable as *mut RetroVariable as *mut c_void,
+        );
+        if !found || variable.value.is_null() {
+            return None;
+        }
+        let value = unsafe { CStr::from_ptr(variable.value) };
+        Some(value.to_string_lossy().into_owned())
+    }
+
+    fn apply_options(&mut self) {
+        let vip = self.variable(PLATFORM_KEY).as_deref() == Some("COSMAC VIP");
+        self.chip8.set_vip_stack(vip);
+        self.chip8.set_stack_depth(if vip {
+            VIP_STACK_DEPTH
+        } else {
+            MAX_STACK_DEPTH
+        });
+        if let Some(cycles) = self.variable(CYCLES_KEY).and_then(|v| v.parse().ok()) {
+            self.chip8.set_cycles_per_frame(cycles);
+        }
+        let policy = self
+            .variable(FAULT_KEY)
+            .and_then(|name| FaultPolicy::from_name(&name))
+            .unwrap_or(FaultPolicy::Ignore);
+        self.chip8.set_fault_policy(policy);
+        let quirks = self
+            .variable(QUIRKS_KEY)
+            .and_then(|name| Quirks::from_name(&name))
+            .unwrap_or_default();
+        self.chip8.set_quirks(quirks);
+    }
+
+    fn poll_keys(&mut self) {
+        if let Some(poll) = self.input_poll {
+            unsafe { poll() };
+        }
+        let Some(state) = self.input_state else {
+            return;
+        };
+        let mut keys = [false; 16];
+        for (id, key, _) in JOYPAD_KEYS {
+            keys[key] |= unsafe { state(0, RETRO_DEVICE_JOYPAD, 0, id) } != 0;
+        }
+        for (id, key) in KEYBOARD_KEYS {
+            keys[key] |= unsafe { state(0, RETRO_DEVICE_KEYBOARD, 0, id as c_uint) } != 0;
+        }
+        for (key, pressed) in keys.into_iter().enumerate() {
+            self.chip8.set_key(key, pressed);
+        }
+    }
+
+    fn run(&mut self) {
+        let mut updated = false;
+        if self.environment(
+            RETRO_ENVIRONMENT_GET_VARIABLE_UPDATE,
+            &mut updated as *mut bool as *mut c_void,
+        ) && updated
+        {
+            self.apply_options();
+        }
+        self.poll_keys();
+
+        // a halted machine just keeps showing its last frame
+        let _ = self.chip8.run_frame();
+
+        self.buzzer.set_pattern(self.chip8.audio_pattern().copied());
+        self.buzzer.set_pitch(self.chip8.audio_pitch());
+        self.buzzer.set_gate(self.chip8.sound_active());
+        self.buzzer.fill(&mut self.samples);
+        if let Some(audio_batch) = self.audio_batch {
+            let stereo: Vec<i16> = self
+                .samples
+                .iter()
+                .flat_map(|sample| {
+                    let sample = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+                    [sample, sample]
+                })
+                .collect();
+            unsafe { audio_batch(stereo.as_ptr(), self.samples.len()) };
+        }
+
+        for (pixel, lit) in self.video.iter_mut().zip(self.chip8.framebuffer()) {
+            *pixel = if *lit { PIXEL_ON } else { PIXEL_OFF };
+        }
+        self.chip8.clear_drawflag();
+        if let Some(video_refresh) = self.video_refresh {
+            unsafe {
+                video_refresh(
+                    self.video.as_ptr() as *const c_void,
+                    SCREEN_WIDTH as c_uint,
+                    SCREEN_HEIGHT as c_uint,
+                    SCREEN_WIDTH * 4,
+                )
+            };
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_api_version() -> c_uint {
+    RETRO_API_VERSION
+}
+
+#[no_mangle]
+pub extern "C" fn retro_init() {
+    let mut core = CORE.lock().unwrap_or_else(|e| e.into_inner());
+    // the frontend can hand over callbacks before init, keep them
+    if core.is_none() {
+        *core = Some(Core::new());
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_deinit() {
+    *CORE.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+fn core_for_callbacks(f: impl FnOnce(&mut Core)) {
+    let mut core = CORE.lock().unwrap_or_else(|e| e.into_inner());
+    f(core.get_or_insert_with(Core::new));
+}
+
+/// # Safety
+/// `environment` must be a valid libretro environment callback.
+#[no_mangle]
+pub unsafe extern "C" fn retro_set_environment(environment: EnvironmentFn) {
+    core_for_callbacks(|core| {
+        core.environment = Some(environment);
+        core.environment(
+            RETRO_ENVIRONMENT_SET_VARIABLES,
+            VARIABLES.as_ptr() as *mut c_void,
+        );
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_video_refresh(video_refresh: VideoRefreshFn) {
+    core_for_callbacks(|core| core.video_refresh = Some(video_refresh));
+}
+
+// everything goes through the batch callback
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample(_audio_sample: AudioSampleFn) {}
+
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample_batch(audio_batch: AudioSampleBatchFn) {
+    core_for_callbacks(|core| core.audio_batch = Some(audio_batch));
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_poll(input_poll: InputPollFn) {
+    core_for_callbacks(|core| core.input_poll = Some(input_poll));
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_state(input_state: InputStateFn) {
+    core_for_callbacks(|core| core.input_state = Some(input_state));
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_controller_port_device(_port: c_uint, _device: c_uint) {}
+
+/// # Safety
+/// `info` must point to a writable retro_system_info.
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_info(info: *mut RetroSystemInfo) {
+    *info = RetroSystemInfo {
+        library_name: c"chip8".as_ptr(),
+        library_version: concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char,
+        valid_extensions: c"ch8|c8|rom".as_ptr(),
+        need_fullpath: false,
+        block_extract: false,
+    };
+}
+
+/// # Safety
+/// `info` must point to a writable retro_system_av_info.
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_av_info(info: *mut RetroSystemAvInfo) {
+    *info = RetroSystemAvInfo {
+        geometry: RetroGameGeometry {
+            base_width: SCREEN_WIDTH as c_uint,
+            base_height: SCREEN_HEIGHT as c_uint,
+            max_width: SCREEN_WIDTH as c_uint,
+            max_height: SCREEN_HEIGHT as c_uint,
+            aspect_ratio: SCREEN_WIDTH as f32 / SCREEN_HEIGHT as f32,
+        },
+        timing: RetroSystemTiming {
+            fps: 60.0,
+            sample_rate: SAMPLE_RATE as f64,
+        },
+    };
+}
+
+/// # Safety
+/// `game` must point to a retro_game_info whose data holds size bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_load_game(game: *const RetroGameInfo) -> bool {
+    if game.is_null() || (*game).data.is_null() {
+        return false;
+    }
+    let rom = std::slice::from_raw_parts((*game).data as *const u8, (*game).size);
+    with_core(|core| {
+        let mut format = RETRO_PIXEL_FORMAT_XRGB8888;
+        if !core.environment(
+            RETRO_ENVIRONMENT_SET_PIXEL_FORMAT,
+            &mut format as *mut c_uint as *mut c_void,
+        ) {
+            return false;
+        }
+        let mut descriptors: Vec<RetroInputDescriptor> = JOYPAD_KEYS
+            .iter()
+            .map(|(id, _, description)| RetroInputDescriptor {
+                port: 0,
+                device: RETRO_DEVICE_JOYPAD,
+                index: 0,
+                id: *id,
+                description: description.as_ptr(),
+            })
+            .collect();
+        descriptors.push(RetroInputDescriptor {
+            port: 0,
+            device: 0,
+            index: 0,
+            id: 0,
+            description: std::ptr::null(),
+        });
+        core.environment(
+            RETRO_ENVIRONMENT_SET_INPUT_DESCRIPTORS,
+            descriptors.as_mut_ptr() as *mut c_void,
+        );
+
+        core.apply_options();
+        core.chip8.load_rom(rom).is_ok()
+    })
+    .unwrap_or(false)
+}
+
+#[no_mangle]
+pub extern "C" fn retro_load_game_special(
+    _game_type: c_uint,
+    _info: *const RetroGameInfo,
+    _num_info: usize,
+) -> bool {
+    false
+}
+
+#[no_mangle]
+pub extern "C" fn retro_unload_game() {
+    with_core(|core| {
+        let _ = core.chip8.load_rom(&[]);
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_region() -> c_uint {
+    RETRO_REGION_NTSC
+}
+
+#[no_mangle]
+pub extern "C" fn retro_reset() {
+    with_core(|core| core.chip8.reset());
+}
+
+#[no_mangle]
+pub extern "C" fn retro_run() {
+    with_core(Core::run);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_serialize_size() -> usize {
+    with_core(|core| core.chip8.save_state().len()).unwrap_or(0)
+}
+
+/// # Safety
+/// `data` must point to size writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_serialize(data: *mut c_void, size: usize) -> bool {
+    let Some(state) = with_core(|core| core.chip8.save_state()) else {
+        return false;
+    };
+    if data.is_null() || size < state.len() {
+        return false;
+    }
+    std::ptr::copy_nonoverlapping(state.as_ptr(), data as *mut u8, state.len());
+    true
+}
+
+/// # Safety
+/// `data` must point to size readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_unserialize(data: *const c_void, size: usize) -> bool {
+    if data.is_null() {
+        return false;
+    }
+    let state = std::slice::from_raw_parts(data as *const u8, size);
+    with_core(|core| core.chip8.load_state(state).is_ok()).unwrap_or(false)
+}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_reset() {}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_set(_index: c_uint, _enabled: bool, _code: *const c_char) {}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_data(_id: c_uint) -> *mut c_void {
+    std::ptr::null_mut()
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_size(_id: c_uint) -> usize {
+    0
+}
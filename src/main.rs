@@ -1,20 +1,392 @@
 extern crate sdl2;
 
-use chip8::Chip8;
+use chip8::browser::{RomBrowser, DEFAULT_ROM_DIR};
+use chip8::cheats::CheatFile;
+use chip8::server::Server;
+use chip8::watch::FileWatcher;
+use chip8::{
+    Audio, Chip8, Emulator, FaultPolicy, Keymap, KeymapConfig, Quirks, Tone, Waveform,
+    MAX_STACK_DEPTH, VIP_STACK_DEPTH,
+};
 use std::env;
+use std::process::Command;
+
+const USAGE: &str = "usage: chip8 [--keymap FILE] [--preset qwerty|numpad|two-player] [--bind]
+             [--tone HZ] [--volume 0-1] [--waveform square|sine|triangle|noise]
+             [--envelope MS] [--audio-out FILE.wav] [--fps] [--rom-dir DIR]
+             [--watch [--watch-source FILE --build CMD] [--keep-rpl] [--keep-state]]
+             [--profile PREFIX] [--coverage PREFIX]
+             [--on-fault ignore|warn|halt|break] [--stack-depth N] [--vip-stack]
+             [--quirks default|chip8|schip|xochip]
+             [--frontend sdl|tui] [--rpc HOST:PORT|unix:PATH [--rpc-remote]]
+             [--cheats FILE] [--script FILE] [ROM]";
+
+struct Options {
+    // without a ROM the rom browser is shown
+    rom: Option<String>,
+    rom_dir: String,
+    keymap: Option<String>,
+    preset: Option<String>,
+    bind: bool,
+    tone: Tone,
+    audio_out: Option<String>,
+    fps: bool,
+    // reload the ROM when it changes on disk
+    watch: bool,
+    // with --watch, rebuild the ROM with this command when the source changes
+    watch_source: Option<String>,
+    build: Option<String>,
+    keep_rpl: bool,
+    keep_state: bool,
+    // write a profile to PREFIX.txt and PREFIX.json on exit
+    profile: Option<String>,
+    // write a coverage map to PREFIX.json, PREFIX.png and PREFIX.asm on exit
+    coverage: Option<String>,
+    // what to do on unknown opcodes, machine code calls, a runaway program counter and stack
+    // faults
+    on_fault: FaultPolicy,
+    // defaults to 12 with --vip-stack and 16 otherwise
+    stack_depth: Option<usize>,
+    // keep the call stack in RAM at 0xEA0 like the COSMAC VIP
+    vip_stack: bool,
+    // name of the quirk preset
+    quirks: String,
+    // run in the terminal instead of an SDL window, needs a ROM
+    tui: bool,
+    // listen for JSON-RPC control requests on this address
+    rpc: Option<String>,
+    // let --rpc listen on addresses other than loopback
+    rpc_remote: bool,
+    // cheat file, see src/cheats.rs
+    cheats: Option<String>,
+    // rhai script with hooks into the emulator, see src/script.rs
+    script: Option<String>,
+}
+
+fn parse_args() -> Options {
+    let mut args = env::args().skip(1);
+    let mut rom = None;
+    let mut rom_dir = DEFAULT_ROM_DIR.to_string();
+    let mut keymap = None;
+    let mut preset = None;
+    let mut bind = false;
+    let mut tone = Tone::default();
+    let mut audio_out = None;
+    let mut fps = false;
+    let mut watch = false;
+    let mut watch_source = None;
+    let mut build = None;
+    let mut keep_rpl = false;
+    let mut keep_state = false;
+    let mut profile = None;
+    let mut coverage = None;
+    let mut on_fault = FaultPolicy::Ignore;
+    let mut quirks = "default".to_string();
+    let mut stack_depth = None;
+    let mut vip_stack = false;
+    let mut tui = false;
+    let mut rpc = None;
+    let mut rpc_remote = false;
+    let mut cheats = None;
+    let mut script = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--keymap" => keymap = Some(args.next().expect(USAGE)),
+            "--preset" => preset = Some(args.next().expect(USAGE)),
+            "--bind" => bind = true,
+            "--fps" => fps = true,
+            "--watch" => watch = true,
+            "--watch-source" => watch_source = Some(args.next().expect(USAGE)),
+            "--build" => build = Some(args.next().expect(USAGE)),
+            "--keep-rpl" => keep_rpl = true,
+            "--keep-state" => keep_state = true,
+            "--profile" => profile = Some(args.next().expect(USAGE)),
+            "--coverage" => coverage = Some(args.next().expect(USAGE)),
+            "--stack-depth" => stack_depth = Some(parse_value(args.next())),
+            "--vip-stack" => vip_stack = true,
+            "--rpc" => rpc = Some(args.next().expect(USAGE)),
+            "--rpc-remote" => rpc_remote = true,
+            "--cheats" => cheats = Some(args.next().expect(USAGE)),
+            "--script" => script = Some(args.next().expect(USAGE)),
+            "--frontend" => {
+                tui = match args.next().as_deref() {
+                    Some("sdl") => false,
+                    Some("tui") => true,
+                    _ => panic!("{}", USAGE),
+                }
+            }
+            "--on-fault" => {
+                on_fault = args
+                    .next()
+                    .and_then(|name| FaultPolicy::from_name(&name))
+                    .expect(USAGE)
+            }
+            "--quirks" => {
+                quirks = args
+                    .next()
+                    .filter(|name| Quirks::from_name(name).is_some())
+                    .expect(USAGE)
+            }
+            "--tone" => tone.frequency = parse_value(args.next()),
+            "--volume" => tone.volume = parse_value(args.next()),
+            "--waveform" => {
+                tone.waveform = args
+                    .next()
+                    .and_then(|name| Waveform::from_name(&name))
+                    .expect(USAGE)
+            }
+            "--rom-dir" => rom_dir = args.next().expect(USAGE),
+            "--audio-out" => audio_out = Some(args.next().expect(USAGE)),
+            "--envelope" => {
+                let ms: f32 = parse_value(args.next());
+                tone.attack = ms / 1000.0;
+                tone.release = ms / 1000.0;
+            }
+            _ => rom = Some(arg),
+        }
+    }
+    // the terminal frontend has no control server, cheat menu or script hooks
+    if tui && (rpc.is_some() || cheats.is_some() || script.is_some()) {
+        eprintln!("--rpc, --cheats and --script need --frontend sdl");
+        std::process::exit(2);
+    }
+
+    Options {
+        rom,
+        rom_dir,
+        keymap,
+        preset,
+        bind,
+        tone,
+        audio_out,
+        fps,
+        watch,
+        watch_source,
+        build,
+        keep_rpl,
+        keep_state,
+        profile,
+        coverage,
+        on_fault,
+        quirks,
+        stack_depth,
+        vip_stack,
+        tui,
+        rpc,
+        rpc_remote,
+        cheats,
+        script,
+    }
+}
+
+fn parse_value<T: std::str::FromStr>(arg: Option<String>) -> T {
+    arg.and_then(|value| value.parse().ok()).expect(USAGE)
+}
+
+// run the build command after the source changed. the ROM watcher picks up the new build
+fn rebuild(emulator: &mut Emulator, build: Option<&str>) {
+    let Some(build) = build else {
+        return;
+    };
+    let status = Command::new("sh").arg("-c").arg(build).status();
+    if !matches!(status, Ok(status) if status.success()) {
+        eprintln!("build failed: {}", build);
+        emulator.media.display.osd.show_message("Build failed");
+    }
+}
+
+// interpreter settings shared by the frontends
+fn configure(chip8: &mut Chip8, options: &Options) {
+    chip8.set_fault_policy(options.on_fault);
+    chip8.set_quirks(Quirks::from_name(&options.quirks).expect(USAGE));
+    chip8.set_vip_stack(options.vip_stack);
+    chip8.set_stack_depth(options.stack_depth.unwrap_or(if options.vip_stack {
+        VIP_STACK_DEPTH
+    } else {
+        MAX_STACK_DEPTH
+    }));
+    if options.profile.is_some() {
+        chip8.enable_profiler();
+    }
+    if options.coverage.is_some() {
+        chip8.enable_coverage();
+    }
+}
+
+fn write_reports(chip8: &Chip8, options: &Options) {
+    if let (Some(prefix), Some(profiler)) = (&options.profile, chip8.profiler()) {
+        if let Err(e) = profiler.write_reports(prefix) {
+            eprintln!("can't write profile {}: {}", prefix, e);
+        }
+    }
+    if let (Some(prefix), Some(coverage)) = (&options.coverage, chip8.coverage()) {
+        if let Err(e) = coverage.write_reports(prefix, chip8.rom()) {
+            eprintln!("can't write coverage {}: {}", prefix, e);
+        }
+    }
+}
+
+#[cfg(feature = "tui")]
+fn run_tui(options: &Options) {
+    let path = options.rom.as_deref().expect(USAGE);
+    let mut chip8 = Chip8::new();
+    configure(&mut chip8, options);
+    let loaded = std::fs::read(path)
+        .map_err(|e| format!("can't read {}: {}", path, e))
+        .and_then(|rom| chip8.load_rom(&rom).map_err(|e| e.to_string()));
+    if let Err(e) = loaded {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+    if let Err(e) = chip8::tui::run(&mut chip8) {
+        eprintln!("terminal error: {}", e);
+    }
+    write_reports(&chip8, options);
+}
+
+#[cfg(not(feature = "tui"))]
+fn run_tui(_options: &Options) {
+    eprintln!("this build has no terminal frontend, rebuild with --features tui");
+}
+
+#[cfg(feature = "scripting")]
+fn attach_script(emulator: &mut Emulator, path: &str) {
+    match chip8::script::Script::load(path) {
+        Ok(script) => emulator.attach_script(script),
+        Err(e) => eprintln!("{}", e),
+    }
+}
+
+#[cfg(not(feature = "scripting"))]
+fn attach_script(_emulator: &mut Emulator, _path: &str) {
+    eprintln!("this build can't run scripts, rebuild with --features scripting");
+}
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    if args.len() < 2 {
-        panic!("ROM not specified");
+    let options = parse_args();
+    if options.tui {
+        run_tui(&options);
+        println!("exiting");
+        return;
     }
 
     let sdl_context = sdl2::init().unwrap();
 
-    let mut chip8 = Chip8::new(&sdl_context);
-    chip8.load_and_init(args[1].as_str());
+    // with --audio-out the buzzer is rendered to a file instead of an audio device
+    let sound = match &options.audio_out {
+        Some(path) => Audio::headless(options.tone, Some(path)).expect("can't create audio file"),
+        None => Audio::with_tone(&sdl_context, options.tone),
+    };
+    let mut emulator = Emulator::with_audio(&sdl_context, sound);
+    configure(&mut emulator.chip8, &options);
+    if let Some(address) = &options.rpc {
+        let server = if options.rpc_remote {
+            Server::bind_remote(address)
+        } else {
+            Server::bind(address)
+        };
+        match server {
+            Ok(server) => {
+                println!("control server listening on {}", server.address());
+                emulator.attach_server(server);
+            }
+            Err(e) => {
+                eprintln!("can't listen on {}: {}", address, e);
+                return;
+            }
+        }
+    }
+
+    if let Some(path) = &options.cheats {
+        match CheatFile::load(path) {
+            Ok(cheats) => emulator.set_cheats(cheats),
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+    if let Some(path) = &options.script {
+        attach_script(&mut emulator, path);
+    }
+
+    let mut config = match &options.keymap {
+        Some(path) => KeymapConfig::load(path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            KeymapConfig::default()
+        }),
+        None => KeymapConfig::default(),
+    };
+    emulator.media.keypad.set_hotkeys(config.hotkeys().clone());
+    emulator.media.display.osd.set_show_counters(options.fps);
+    emulator
+        .media
+        .display
+        .osd
+        .set_preset(Some(options.quirks.clone()));
 
+    let mut browser = RomBrowser::new(&options.rom_dir);
+    let mut rom = options.rom.clone();
+    let mut bind = options.bind;
     loop {
-        chip8.emulate_cycle();
+        let path = match rom.take() {
+            Some(path) => path,
+            None => match browser.choose(&mut emulator.media) {
+                Some(path) => path,
+                None => break,
+            },
+        };
+
+        let mut keymap = config.for_rom(&path);
+        if let Some(name) = &options.preset {
+            keymap = Keymap::preset(name).expect(USAGE);
+        }
+        emulator.media.keypad.set_keymap(keymap);
+        if bind {
+            bind = false;
+            if let Some(bound) = emulator.bind_keys() {
+                // remember the new bindings for this ROM
+                if let Some(keymap_path) = &options.keymap {
+                    config.set(&KeymapConfig::section_name(&path), bound.clone());
+                    if let Err(e) = config.save(keymap_path) {
+                        eprintln!("{}", e);
+                    }
+                }
+                emulator.media.keypad.set_keymap(bound);
+            }
+        }
+
+        if let Err(e) = emulator.load_and_init(path.as_str()) {
+            // back to the browser, which shows the message
+            eprintln!("{}", e);
+            emulator.media.display.osd.show_message(&e);
+            continue;
+        }
+        browser.add_recent(&path);
+
+        let mut rom_watcher = options.watch.then(|| FileWatcher::new(&[&path]));
+        let mut source_watcher = match (&options.watch_source, options.watch) {
+            (Some(source), true) => Some(FileWatcher::new(&[source])),
+            _ => None,
+        };
+        while emulator.is_running() && !emulator.take_menu_request() {
+            if let Some(watcher) = &mut source_watcher {
+                if watcher.changed() {
+                    rebuild(&mut emulator, options.build.as_deref());
+                }
+            }
+            if let Some(watcher) = &mut rom_watcher {
+                if watcher.changed() {
+                    match emulator.reload_rom(&path, options.keep_rpl, options.keep_state) {
+                        Ok(()) => emulator.media.display.osd.show_message("ROM reloaded"),
+                        Err(e) => eprintln!("can't reload {}: {}", path, e),
+                    }
+                }
+            }
+            emulator.emulate_frame();
+        }
+        if !emulator.is_running() {
+            break;
+        }
     }
+    write_reports(&emulator.chip8, &options);
+    println!("exiting");
 }
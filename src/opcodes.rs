@@ -0,0 +1,205 @@
+// the instruction an opcode belongs to. opcodes the interpreter doesn't know are Unknown
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Class {
+    Cls,
+    Ret,
+    Sys,
+    Jump,
+    Call,
+    SkipEqualByte,
+    SkipNotEqualByte,
+    SkipEqual,
+    LoadByte,
+    AddByte,
+    Load,
+    Or,
+    And,
+    Xor,
+    Add,
+    Sub,
+    ShiftRight,
+    SubReverse,
+    ShiftLeft,
+    SkipNotEqual,
+    LoadIndex,
+    JumpOffset,
+    Random,
+    Draw,
+    SkipKey,
+    SkipNotKey,
+    Audio,
+    LoadDelay,
+    WaitKey,
+    SetDelay,
+    SetSound,
+    AddIndex,
+    LoadFont,
+    Bcd,
+    Pitch,
+    Store,
+    Restore,
+    StoreFlags,
+    RestoreFlags,
+    Unknown,
+}
+
+impl Class {
+    // the instruction written the usual way, with the operand nibbles as letters
+    pub fn pattern(&self) -> &'static str {
+        match self {
+            Class::Cls => "00E0",
+            Class::Ret => "00EE",
+            Class::Sys => "0NNN",
+            Class::Jump => "1NNN",
+            Class::Call => "2NNN",
+            Class::SkipEqualByte => "3XNN",
+            Class::SkipNotEqualByte => "4XNN",
+            Class::SkipEqual => "5XY0",
+            Class::LoadByte => "6XNN",
+            Class::AddByte => "7XNN",
+            Class::Load => "8XY0",
+            Class::Or => "8XY1",
+            Class::And => "8XY2",
+            Class::Xor => "8XY3",
+            Class::Add => "8XY4",
+            Class::Sub => "8XY5",
+            Class::ShiftRight => "8XY6",
+            Class::SubReverse => "8XY7",
+            Class::ShiftLeft => "8XYE",
+            Class::SkipNotEqual => "9XY0",
+            Class::LoadIndex => "ANNN",
+            Class::JumpOffset => "BNNN",
+            Class::Random => "CXNN",
+            Class::Draw => "DXYN",
+            Class::SkipKey => "EX9E",
+            Class::SkipNotKey => "EXA1",
+            Class::Audio => "F002",
+            Class::LoadDelay => "FX07",
+            Class::WaitKey => "FX0A",
+            Class::SetDelay => "FX15",
+            Class::SetSound => "FX18",
+            Class::AddIndex => "FX1E",
+            Class::LoadFont => "FX29",
+            Class::Bcd => "FX33",
+            Class::Pitch => "FX3A",
+            Class::Store => "FX55",
+            Class::Restore => "FX65",
+            Class::StoreFlags => "FX75",
+            Class::RestoreFlags => "FX85",
+            Class::Unknown => "unknown",
+        }
+    }
+}
+
+impl std::fmt::Display for Class {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(self.pattern())
+    }
+}
+
+pub fn class(opcode: u16) -> Class {
+    match opcode & 0xf000 {
+        0x0000 => match opcode {
+            0x00e0 => Class::Cls,
+            0x00ee => Class::Ret,
+            _ => Class::Sys,
+        },
+        0x1000 => Class::Jump,
+        0x2000 => Class::Call,
+        0x3000 => Class::SkipEqualByte,
+        0x4000 => Class::SkipNotEqualByte,
+        0x5000 if opcode & 0x000f == 0 => Class::SkipEqual,
+        0x6000 => Class::LoadByte,
+        0x7000 => Class::AddByte,
+        0x8000 => match opcode & 0x000f {
+            0x0 => Class::Load,
+            0x1 => Class::Or,
+            0x2 => Class::And,
+            0x3 => Class::Xor,
+            0x4 => Class::Add,
+            0x5 => Class::Sub,
+            0x6 => Class::ShiftRight,
+            0x7 => Class::SubReverse,
+            0xe => Class::ShiftLeft,
+            _ => Class::Unknown,
+        },
+        0x9000 if opcode & 0x000f == 0 => Class::SkipNotEqual,
+        0xa000 => Class::LoadIndex,
+        0xb000 => Class::JumpOffset,
+        0xc000 => Class::Random,
+        0xd000 => Class::Draw,
+        0xe000 => match opcode & 0x00ff {
+            0x9e => Class::SkipKey,
+            0xa1 => Class::SkipNotKey,
+            _ => Class::Unknown,
+        },
+        0xf000 => match opcode & 0x00ff {
+            0x02 if opcode == 0xf002 => Class::Audio,
+            0x07 => Class::LoadDelay,
+            0x0a => Class::WaitKey,
+            0x15 => Class::SetDelay,
+            0x18 => Class::SetSound,
+            0x1e => Class::AddIndex,
+            0x29 => Class::LoadFont,
+            0x33 => Class::Bcd,
+            0x3a => Class::Pitch,
+            0x55 => Class::Store,
+            0x65 => Class::Restore,
+            0x75 => Class::StoreFlags,
+            0x85 => Class::RestoreFlags,
+            _ => Class::Unknown,
+        },
+        _ => Class::Unknown,
+    }
+}
+
+// assembly for an opcode, in the usual CHIP-8 mnemonics. unknown opcodes come out as data
+pub fn mnemonic(opcode: u16) -> String {
+    let x = (opcode & 0x0f00) >> 8;
+    let y = (opcode & 0x00f0) >> 4;
+    let n = opcode & 0x000f;
+    let nn = opcode & 0x00ff;
+    let nnn = opcode & 0x0fff;
+    match class(opcode) {
+        Class::Cls => "CLS".to_string(),
+        Class::Ret => "RET".to_string(),
+        Class::Sys => format!("SYS  {:03X}", nnn),
+        Class::Jump => format!("JP   {:03X}", nnn),
+        Class::Call => format!("CALL {:03X}", nnn),
+        Class::SkipEqualByte => format!("SE   V{:X}, {:02X}", x, nn),
+        Class::SkipNotEqualByte => format!("SNE  V{:X}, {:02X}", x, nn),
+        Class::SkipEqual => format!("SE   V{:X}, V{:X}", x, y),
+        Class::LoadByte => format!("LD   V{:X}, {:02X}", x, nn),
+        Class::AddByte => format!("ADD  V{:X}, {:02X}", x, nn),
+        Class::Load => format!("LD   V{:X}, V{:X}", x, y),
+        Class::Or => format!("OR   V{:X}, V{:X}", x, y),
+        Class::And => format!("AND  V{:X}, V{:X}", x, y),
+        Class::Xor => format!("XOR  V{:X}, V{:X}", x, y),
+        Class::Add => format!("ADD  V{:X}, V{:X}", x, y),
+        Class::Sub => format!("SUB  V{:X}, V{:X}", x, y),
+        Class::ShiftRight => format!("SHR  V{:X}, V{:X}", x, y),
+        Class::SubReverse => format!("SUBN V{:X}, V{:X}", x, y),
+        Class::ShiftLeft => format!("SHL  V{:X}, V{:X}", x, y),
+        Class::SkipNotEqual => format!("SNE  V{:X}, V{:X}", x, y),
+        Class::LoadIndex => format!("LD   I, {:03X}", nnn),
+        Class::JumpOffset => format!("JP   V0, {:03X}", nnn),
+        Class::Random => format!("RND  V{:X}, {:02X}", x, nn),
+        Class::Draw => format!("DRW  V{:X}, V{:X}, {:X}", x, y, n),
+        Class::SkipKey => format!("SKP  V{:X}", x),
+        Class::SkipNotKey => format!("SKNP V{:X}", x),
+        Class::Audio => "AUDIO".to_string(),
+        Class::LoadDelay => format!("LD   V{:X}, DT", x),
+        Class::WaitKey => format!("LD   V{:X}, K", x),
+        Class::SetDelay => format!("LD   DT, V{:X}", x),
+        Class::SetSound => format!("LD   ST, V{:X}", x),
+        Class::AddIndex => format!("ADD  I, V{:X}", x),
+        Class::LoadFont => format!("LD   F, V{:X}", x),
+        Class::Bcd => format!("LD   B, V{:X}", x),
+        Class::Pitch => format!("PITCH V{:X}", x),
+        Class::Store => format!("LD   [I], V{:X}", x),
+        Class::Restore => format!("LD   V{:X}, [I]", x),
+        Class::StoreFlags => format!("LD   R, V{:X}", x),
+        Class::RestoreFlags => format!("LD   V{:X}, R", x),
+        Class::Unknown => format!("dw   {:04X}", opcode),
+    }
+}
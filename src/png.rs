@@ -0,0 +1,56 @@
+use std::fs;
+use std::io;
+
+use crate::checksum;
+
+// deflate stored blocks hold at most this many bytes
+const STORED_BLOCK_LEN: usize = 65535;
+
+fn chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = checksum::crc32_update(checksum::crc32(kind), data);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+// encode an 8-bit RGB image as a PNG. the image data is stored uncompressed, which keeps the
+// encoder tiny and is fine for the small images written here
+pub fn encode_rgb(width: u32, height: u32, rgb: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(b"\x89PNG\r\n\x1a\n");
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // bit depth 8, colour type 2 (rgb), default compression, filter and interlace
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+    chunk(&mut out, b"IHDR", &header);
+
+    // every scanline starts with filter type 0
+    let stride = width as usize * 3;
+    let mut raw = Vec::with_capacity((stride + 1) * height as usize);
+    for row in rgb.chunks(stride).take(height as usize) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+
+    let mut zlib = vec![0x78, 0x01];
+    let blocks: Vec<&[u8]> = raw.chunks(STORED_BLOCK_LEN).collect();
+    for (n, block) in blocks.iter().enumerate() {
+        zlib.push((n + 1 == blocks.len()) as u8);
+        let len = block.len() as u16;
+        zlib.extend_from_slice(&len.to_le_bytes());
+        zlib.extend_from_slice(&(!len).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&checksum::adler32(&raw).to_be_bytes());
+    chunk(&mut out, b"IDAT", &zlib);
+
+    chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+pub fn write_rgb(path: &str, width: u32, height: u32, rgb: &[u8]) -> io::Result<()> {
+    fs::write(path, encode_rgb(width, height, rgb))
+}
@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::opcodes;
+
+// how many addresses the text report lists
+const HOTSPOTS: usize = 20;
+
+// the ROM entry point, everything not inside a subroutine is counted against it
+const ENTRY_POINT: u16 = 0x200;
+
+#[derive(Default, Clone, Copy)]
+pub struct RoutineStats {
+    pub calls: u64,
+    // instructions run inside the routine and everything it called
+    pub inclusive: u64,
+    // instructions run inside the routine itself
+    pub exclusive: u64,
+}
+
+struct Frame {
+    routine: u16,
+    // instruction count when the routine was entered
+    entered_at: u64,
+}
+
+// counts instructions per address and per opcode class, and builds a call graph from 2NNN and
+// 00EE pairs
+pub struct Profiler {
+    total: u64,
+    pc_counts: Vec<u64>,
+    // last opcode seen at each address, for the report
+    pc_opcodes: Vec<u16>,
+    class_counts: HashMap<&'static str, u64>,
+    stack: Vec<Frame>,
+    routines: HashMap<u16, RoutineStats>,
+    // (caller, callee) -> number of calls
+    edges: HashMap<(u16, u16), u64>,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        let mut profiler = Self {
+            total: 0,
+            pc_counts: vec![0; 4096],
+            pc_opcodes: vec![0; 4096],
+            class_counts: HashMap::new(),
+            stack: Vec::new(),
+            routines: HashMap::new(),
+            edges: HashMap::new(),
+        };
+        profiler.enter(ENTRY_POINT);
+        profiler
+    }
+}
+
+impl Profiler {
+    fn enter(&mut self, routine: u16) {
+        self.routines.entry(routine).or_default().calls += 1;
+        self.stack.push(Frame {
+            routine,
+            entered_at: self.total,
+        });
+    }
+
+    fn is_on_stack(&self, routine: u16) -> bool {
+        self.stack.iter().any(|frame| frame.routine == routine)
+    }
+
+    fn current_routine(&self) -> u16 {
+        self.stack.last().map_or(ENTRY_POINT, |frame| frame.routine)
+    }
+
+    // called for every instruction that executed without a fault
+    pub fn record(&mut self, pc: u16, opcode: u16) {
+        self.total += 1;
+        let address = pc as usize & 0xfff;
+        self.pc_counts[address] += 1;
+        self.pc_opcodes[address] = opcode;
+        *self
+            .class_counts
+            .entry(opcodes::class(opcode).pattern())
+            .or_default() += 1;
+
+        let routine = self.current_routine();
+        self.routines.entry(routine).or_default().exclusive += 1;
+
+        if opcode & 0xf000 == 0x2000 {
+            let callee = opcode & 0x0fff;
+            *self.edges.entry((routine, callee)).or_default() += 1;
+            self.enter(callee);
+        } else if opcode == 0x00ee && self.stack.len() > 1 {
+            // the return counts towards the routine it returns from. a recursive call is already
+            // covered by the outermost frame of the routine
+            let frame = self.stack.pop().unwrap();
+            if !self.is_on_stack(frame.routine) {
+                self.routines.entry(frame.routine).or_default().inclusive +=
+                    self.total - frame.entered_at;
+            }
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    pub fn count_at(&self, address: u16) -> u64 {
+        self.pc_counts[address as usize & 0xfff]
+    }
+
+    pub fn class_counts(&self) -> &HashMap<&'static str, u64> {
+        &self.class_counts
+    }
+
+    // per routine stats, with the routines still on the call stack counted up to now
+    pub fn routines(&self) -> Vec<(u16, RoutineStats)> {
+        let mut routines = self.routines.clone();
+        for (n, frame) in self.stack.iter().enumerate() {
+            let outermost = !self.stack[..n]
+                .iter()
+                .any(|outer| outer.routine == frame.routine);
+            if outermost {
+                routines.entry(frame.routine).or_default().inclusive +=
+                    self.total - frame.entered_at;
+            }
+        }
+        let mut routines: Vec<(u16, RoutineStats)> = routines.into_iter().collect();
+        routines.sort_by(|a, b| b.1.inclusive.cmp(&a.1.inclusive).then(a.0.cmp(&b.0)));
+        routines
+    }
+
+    pub fn edges(&self) -> Vec<((u16, u16), u64)> {
+        let mut edges: Vec<((u16, u16), u64)> = self
+            .edges
+            .iter()
+            .map(|(edge, calls)| (*edge, *calls))
+            .collect();
+        edges.sort();
+        edges
+    }
+
+    fn hotspots(&self) -> Vec<(u16, u64)> {
+        let mut hotspots: Vec<(u16, u64)> = self
+            .pc_counts
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .map(|(address, count)| (address as u16, *count))
+            .collect();
+        hotspots.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        hotspots
+    }
+
+    fn classes(&self) -> Vec<(&'static str, u64)> {
+        let mut classes: Vec<(&'static str, u64)> = self
+            .class_counts
+            .iter()
+            .map(|(class, count)| (*class, *count))
+            .collect();
+        classes.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        classes
+    }
+
+    fn percent(&self, count: u64) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            count as f64 * 100.0 / self.total as f64
+        }
+    }
+
+    pub fn report_text(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "{} instructions", self.total);
+
+        let _ = writeln!(out, "\nhotspots\n  address  opcode  count       %");
+        for (address, count) in self.hotspots().into_iter().take(HOTSPOTS) {
+            let _ = writeln!(
+                out,
+                "  {:03X}      {:04X}    {:<10}  {:5.1}",
+                address,
+                self.pc_opcodes[address as usize],
+                count,
+                self.percent(count)
+            );
+        }
+
+        let _ = writeln!(out, "\nopcode classes\n  class    count       %");
+        for (class, count) in self.classes() {
+            let _ = writeln!(
+                out,
+                "  {:<7}  {:<10}  {:5.1}",
+                class,
+                count,
+                self.percent(count)
+            );
+        }
+
+        let _ = writeln!(
+            out,
+            "\nsubroutines\n  address  calls     inclusive   exclusive   incl/call"
+        );
+        for (address, stats) in self.routines() {
+            let _ = writeln!(
+                out,
+                "  {:03X}      {:<8}  {:<10}  {:<10}  {:.1}",
+                address,
+                stats.calls,
+                stats.inclusive,
+                stats.exclusive,
+                stats.inclusive as f64 / stats.calls.max(1) as f64
+            );
+        }
+
+        let _ = writeln!(out, "\ncall graph\n  caller -> callee  calls");
+        for ((caller, callee), calls) in self.edges() {
+            let _ = writeln!(out, "  {:03X}    -> {:03X}     {}", caller, callee, calls);
+        }
+        out
+    }
+
+    pub fn report_json(&self) -> String {
+        let hotspots: Vec<String> = self
+            .hotspots()
+            .into_iter()
+            .map(|(address, count)| {
+                format!(
+                    "{{\"address\":{},\"opcode\":{},\"count\":{}}}",
+                    address, self.pc_opcodes[address as usize], count
+                )
+            })
+            .collect();
+        let classes: Vec<String> = self
+            .classes()
+            .into_iter()
+            .map(|(class, count)| format!("\"{}\":{}", class, count))
+            .collect();
+        let routines: Vec<String> = self
+            .routines()
+            .into_iter()
+            .map(|(address, stats)| {
+                format!(
+                    "{{\"address\":{},\"calls\":{},\"inclusive\":{},\"exclusive\":{}}}",
+                    address, stats.calls, stats.inclusive, stats.exclusive
+                )
+            })
+            .collect();
+        let edges: Vec<String> = self
+            .edges()
+            .into_iter()
+            .map(|((caller, callee), calls)| {
+                format!(
+                    "{{\"caller\":{},\"callee\":{},\"calls\":{}}}",
+                    caller, callee, calls
+                )
+            })
+            .collect();
+
+        format!(
+            "{{\"total\":{},\"hotspots\":[{}],\"classes\":{{{}}},\"routines\":[{}],\"calls\":[{}]}}\n",
+            self.total,
+            hotspots.join(","),
+            classes.join(","),
+            routines.join(","),
+            edges.join(",")
+        )
+    }
+
+    // write <prefix>.txt and <prefix>.json
+    pub fn write_reports(&self, prefix: &str) -> std::io::Result<()> {
+        std::fs::write(format!("{}.txt", prefix), self.report_text())?;
+        std::fs::write(format!("{}.json", prefix), self.report_json())
+    }
+}
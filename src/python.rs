@@ -0,0 +1,217 @@
+use pyo3::create_exception;
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyMemoryView};
+
+use crate::{Step, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+create_exception!(chip8, Chip8Error, PyRuntimeError);
+
+fn chip8_error(error: crate::Chip8Error) -> PyErr {
+    Chip8Error::new_err(error.to_string())
+}
+
+// a read-only memoryview over a copy of data, shaped like the numpy array it becomes with
+// numpy.asarray
+fn view<'py>(py: Python<'py>, data: &[u8], shape: (usize, usize)) -> PyResult<Bound<'py, PyAny>> {
+    let bytes = PyBytes::new(py, data);
+    PyMemoryView::from(&bytes)?.call_method1("cast", ("B", shape))
+}
+
+// the interpreter for python. faults that stop the machine raise chip8.Chip8Error
+#[pyclass(name = "Chip8", module = "chip8")]
+pub struct PyChip8 {
+    chip8: crate::Chip8,
+}
+
+#[pymethods]
+impl PyChip8 {
+    #[classattr]
+    const WIDTH: usize = SCREEN_WIDTH;
+    #[classattr]
+    const HEIGHT: usize = SCREEN_HEIGHT;
+
+    #[new]
+    fn new() -> Self {
+        Self {
+            chip8: crate::Chip8::new(),
+        }
+    }
+
+    fn load_rom(&mut self, rom: &[u8]) -> PyResult<()> {
+        self.chip8.load_rom(rom).map_err(chip8_error)
+    }
+
+    fn reset(&mut self) {
+        self.chip8.reset();
+    }
+
+    // one instruction. (address, opcode) of what ran, None while FX0A waits for a key or when the
+    // fault policy skipped the instruction
+    fn step(&mut self) -> PyResult<Option<(u16, u16)>> {
+        match self.chip8.step().map_err(chip8_error)? {
+            Step::Executed { address, opcode } => Ok(Some((address, opcode))),
+            Step::WaitingForKey | Step::Skipped(_) => Ok(None),
+        }
+    }
+
+    // one 60Hz frame: cycles_per_frame instructions and a timer tick
+    fn run_frame(&mut self) -> PyResult<()> {
+        self.chip8.run_frame().map_err(chip8_error)
+    }
+
+    #[getter]
+    fn cycles_per_frame(&self) -> u32 {
+        self.chip8.cycles_per_frame()
+    }
+
+    #[setter]
+    fn set_cycles_per_frame(&mut self, cycles: u32) {
+        self.chip8.set_cycles_per_frame(cycles);
+    }
+
+    // HEIGHT x WIDTH bytes, 1 for lit pixels. numpy.asarray(chip8.framebuffer) gives a 32x64
+    // uint8 array
+    #[getter]
+    fn framebuffer<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let pixels: Vec<u8> = self
+            .chip8
+            .framebuffer()
+            .iter()
+            .map(|pixel| *pixel as u8)
+            .collect();
+        view(py, &pixels, (SCREEN_HEIGHT, SCREEN_WIDTH))
+    }
+
+    // true when the screen changed since the last call
+    fn take_draw_flag(&mut self) -> bool {
+        let draw = self.chip8.is_drawflag_set();
+        self.chip8.clear_drawflag();
+        draw
+    }
+
+    #[getter]
+    fn pc(&self) -> u16 {
+        self.chip8.pc()
+    }
+
+    #[setter]
+    fn set_pc(&mut self, pc: u16) {
+        self.chip8.set_pc(pc);
+    }
+
+    #[getter]
+    fn i(&self) -> u16 {
+        self.chip8.index()
+    }
+
+    #[setter]
+    fn set_i(&mut self, index: u16) {
+        self.chip8.set_index(index);
+    }
+
+    #[getter]
+    fn sp(&self) -> u16 {
+        self.chip8.sp()
+    }
+
+    #[getter]
+    fn stack(&self) -> Vec<u16> {
+        self.chip8.stack()
+    }
+
+    #[getter]
+    fn dt(&self) -> u8 {
+        self.chip8.delay_timer()
+    }
+
+    #[setter]
+    fn set_dt(&mut self, value: u8) {
+        self.chip8.set_delay_timer(value);
+    }
+
+    #[getter]
+    fn st(&self) -> u8 {
+        self.chip8.sound_timer()
+    }
+
+    #[setter]
+    fn set_st(&mut self, value: u8) {
+        self.chip8.set_sound_timer(value);
+    }
+
+    // V0 to VF as bytes, assign 16 values to set them all
+    #[getter]
+    fn v<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, self.chip8.v())
+    }
+
+    #[setter]
+    fn set_v(&mut self, values: Vec<u8>) -> PyResult<()> {
+        if values.len() != 16 {
+            return Err(PyValueError::new_err("V0 to VF are 16 registers"));
+        }
+        for (x, value) in values.into_iter().enumerate() {
+            self.chip8.set_v(x, value);
+        }
+        Ok(())
+    }
+
+    // a snapshot of the 4K of memory, write it back with write_memory
+    #[getter]
+    fn memory<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyMemoryView>> {
+        PyMemoryView::from(&PyBytes::new(py, self.chip8.memory()))
+    }
+
+    fn read_memory<'py>(
+        &self,
+        py: Python<'py>,
+        address: usize,
+        length: usize,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        let memory = self.chip8.memory();
+        let end = address
+            .checked_add(length)
+            .filter(|end| *end <= memory.len())
+            .ok_or_else(|| PyValueError::new_err("outside of memory"))?;
+        Ok(PyBytes::new(py, &memory[address..end]))
+    }
+
+    fn write_memory(&mut self, address: usize, data: &[u8]) -> PyResult<()> {
+        let memory = self.chip8.memory_mut();
+        let end = address
+            .checked_add(data.len())
+            .filter(|end| *end <= memory.len())
+            .ok_or_else(|| PyValueError::new_err("outside of memory"))?;
+        memory[address..end].copy_from_slice(data);
+        Ok(())
+    }
+
+    fn set_key(&mut self, key: usize, pressed: bool) -> PyResult<()> {
+        if key > 0xf {
+            return Err(PyValueError::new_err("keys go from 0 to 15"));
+        }
+        self.chip8.set_key(key, pressed);
+        Ok(())
+    }
+
+    #[getter]
+    fn sound_active(&self) -> bool {
+        self.chip8.sound_active()
+    }
+
+    fn save_state<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.chip8.save_state())
+    }
+
+    fn load_state(&mut self, state: &[u8]) -> PyResult<()> {
+        self.chip8.load_state(state).map_err(PyValueError::new_err)
+    }
+}
+
+#[pymodule]
+fn chip8(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyChip8>()?;
+    m.add("Chip8Error", m.py().get_type::<Chip8Error>())?;
+    Ok(())
+}
@@ -0,0 +1,72 @@
+// instructions that behave differently between CHIP-8 interpreters. games are written against
+// one of them and may break on the others, so the behaviour can be picked per ROM
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Quirks {
+    // 8XY6 and 8XYE shift Vy into Vx, instead of shifting Vx in place
+    pub shift_vy: bool,
+    // FX55 and FX65 leave I pointing past the last register they touched
+    pub load_increments_i: bool,
+    // BNNN jumps to XNN + VX, instead of NNN + V0
+    pub jump_vx: bool,
+    // 8XY1, 8XY2 and 8XY3 clear VF, a side effect of how the VIP ran them
+    pub vf_reset: bool,
+}
+
+// the quirks of the platforms most games are written for, by name
+const PRESETS: [(&str, Quirks); 4] = [
+    // what this interpreter always did, and what most CHIP-8 games found online expect
+    ("default", Quirks::DEFAULT),
+    // the original COSMAC VIP interpreter
+    (
+        "chip8",
+        Quirks {
+            shift_vy: true,
+            load_increments_i: true,
+            jump_vx: false,
+            vf_reset: true,
+        },
+    ),
+    // SUPER-CHIP 1.1 on the HP48
+    (
+        "schip",
+        Quirks {
+            shift_vy: false,
+            load_increments_i: false,
+            jump_vx: true,
+            vf_reset: false,
+        },
+    ),
+    // Octo's XO-CHIP, which went back to the VIP shifts and loads but leaves VF alone
+    (
+        "xochip",
+        Quirks {
+            shift_vy: true,
+            load_increments_i: true,
+            jump_vx: false,
+            vf_reset: false,
+        },
+    ),
+];
+
+impl Quirks {
+    pub const DEFAULT: Quirks = Quirks {
+        shift_vy: false,
+        load_increments_i: false,
+        jump_vx: false,
+        vf_reset: false,
+    };
+
+    // default, chip8, schip or xochip
+    pub fn from_name(name: &str) -> Option<Self> {
+        PRESETS
+            .iter()
+            .find(|(preset, _)| *preset == name)
+            .map(|(_, quirks)| *quirks)
+    }
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Quirks::DEFAULT
+    }
+}
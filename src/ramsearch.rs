@@ -0,0 +1,120 @@
+// RAM search, for finding where a game keeps a variable like its score or lives. start with every
+// address as a candidate, play until the variable changes in a known way and keep the candidates
+// whose value relates to the snapshot the same way. every pass takes a new snapshot, so passes can
+// be repeated until only a few candidates are left
+use crate::Chip8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Relation {
+    // same as in the snapshot
+    Equal,
+    Changed,
+    Increased,
+    Decreased,
+    EqualTo(u8),
+}
+
+impl Relation {
+    fn holds(&self, previous: u8, value: u8) -> bool {
+        match *self {
+            Relation::Equal => value == previous,
+            Relation::Changed => value != previous,
+            Relation::Increased => value > previous,
+            Relation::Decreased => value < previous,
+            Relation::EqualTo(expected) => value == expected,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Candidate {
+    pub address: u16,
+    // value in the last snapshot
+    pub previous: u8,
+    pub value: u8,
+}
+
+pub struct RamSearch {
+    snapshot: Vec<u8>,
+    candidates: Vec<u16>,
+    passes: u32,
+}
+
+impl RamSearch {
+    // snapshot memory with every address as a candidate
+    pub fn new(chip8: &Chip8) -> Self {
+        let snapshot = chip8.memory().to_vec();
+        Self {
+            candidates: (0..snapshot.len() as u16).collect(),
+            snapshot,
+            passes: 0,
+        }
+    }
+
+    // keep the candidates whose value relates to the snapshot, then take a new snapshot. returns
+    // how many are left
+    pub fn filter(&mut self, chip8: &Chip8, relation: Relation) -> usize {
+        let memory = chip8.memory();
+        let snapshot = &self.snapshot;
+        self.candidates.retain(|address| {
+            let address = *address as usize;
+            relation.holds(snapshot[address], memory[address])
+        });
+        self.snapshot.copy_from_slice(memory);
+        self.passes += 1;
+        self.candidates.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.candidates.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.candidates.is_empty()
+    }
+
+    // filters applied since the search started
+    pub fn passes(&self) -> u32 {
+        self.passes
+    }
+
+    pub fn candidates(&self, chip8: &Chip8) -> Vec<Candidate> {
+        let memory = chip8.memory();
+        self.candidates
+            .iter()
+            .map(|address| Candidate {
+                address: *address,
+                previous: self.snapshot[*address as usize],
+                value: memory[*address as usize],
+            })
+            .collect()
+    }
+}
+
+// a byte to keep an eye on. it is compared between frames, so a host can stop at the end of the
+// frame that wrote it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub address: u16,
+    value: u8,
+}
+
+impl Watchpoint {
+    pub fn new(chip8: &Chip8, address: u16) -> Self {
+        Self {
+            address,
+            value: chip8.memory()[address as usize],
+        }
+    }
+
+    // the value it had and the one it has now, if it changed since the last check
+    pub fn check(&mut self, chip8: &Chip8) -> Option<(u8, u8)> {
+        let value = chip8.memory()[self.address as usize];
+        let previous = std::mem::replace(&mut self.value, value);
+        (value != previous).then_some((previous, value))
+    }
+
+    pub fn value(&self) -> u8 {
+        self.value
+    }
+}
@@ -0,0 +1,23 @@
+use crate::checksum;
+
+// titles of known ROMs, by rom_hash
+const KNOWN_ROMS: [(u32, &str); 5] = [
+    (0xd106c808, "Blitz (David Winter)"),
+    (0xaaa44d0b, "Brix (Andreas Gustafsson)"),
+    (0xead625b8, "Space Invaders (David Winter)"),
+    (0x37a658a2, "Maze (David Winter)"),
+    (0x7d75a857, "Pong (Paul Vervalin)"),
+];
+
+// ROMs are identified by the crc32 of the whole file
+pub fn rom_hash(rom: &[u8]) -> u32 {
+    checksum::crc32(rom)
+}
+
+pub fn title_for(rom: &[u8]) -> Option<&'static str> {
+    let hash = rom_hash(rom);
+    KNOWN_ROMS
+        .iter()
+        .find(|(known, _)| *known == hash)
+        .map(|(_, title)| *title)
+}
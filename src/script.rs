@@ -0,0 +1,336 @@
+// rhai scripts with hooks into the emulator, for automated test scenarios, bots and overlays. the
+// top level of a script runs before the first frame and registers callbacks:
+//
+//  on_frame(|| { ... })              after every frame
+//  on_pc(0x2ca, |pc| { ... })        before the instruction at an address runs
+//  on_write(0x314, |address, value| { ... })
+//                                    after an instruction stored to an address
+//  on_sound(|on| { ... })            when the buzzer starts or stops
+//
+// from anywhere in the script:
+//
+//  pc() i() dt() st() v(x)           read the registers
+//  set_pc(n) set_i(n) set_dt(n) set_st(n) set_v(x, n)
+//  peek(address) poke(address, n)    read and write memory
+//  pixel(x, y)                       whether a pixel is lit
+//  press(key) release(key)           hold keys down on top of the keyboard
+//  frame()                           frames run since the script started
+//  draw_text(row, text) clear_text() text over the screen
+//  screenshot(path)                  the screen as a PNG, a pixel per chip8 pixel
+//  reset() quit()
+//
+// a script error stops the hooks, the host reports it and carries on without the script
+use std::cell::RefCell;
+use std::fs;
+use std::rc::Rc;
+
+use rhai::{Dynamic, Engine, EvalAltResult, FnPtr, FuncArgs, AST, INT};
+
+use crate::{png, Chip8, Chip8Error, Step, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+// what the script functions work on. the host's interpreter is moved in here for the duration of
+// every callback
+#[derive(Default)]
+struct Context {
+    chip8: Chip8,
+    keys: [bool; 16],
+    overlay: Vec<String>,
+    frame: INT,
+    quit: bool,
+}
+
+#[derive(Default)]
+struct Hooks {
+    frame: Vec<FnPtr>,
+    pc: Vec<(u16, FnPtr)>,
+    write: Vec<(u16, FnPtr)>,
+    sound: Vec<FnPtr>,
+}
+
+fn address(address: INT) -> ScriptResult<usize> {
+    usize::try_from(address)
+        .ok()
+        .filter(|address| *address < 0x1000)
+        .ok_or_else(|| format!("address {} is outside of memory", address).into())
+}
+
+fn register(x: INT) -> ScriptResult<usize> {
+    usize::try_from(x)
+        .ok()
+        .filter(|x| *x < 16)
+        .ok_or_else(|| format!("no register V{}", x).into())
+}
+
+fn key(key: INT) -> ScriptResult<usize> {
+    usize::try_from(key)
+        .ok()
+        .filter(|key| *key < 16)
+        .ok_or_else(|| format!("no key {}", key).into())
+}
+
+fn register_api(engine: &mut Engine, context: &Rc<RefCell<Context>>, hooks: &Rc<RefCell<Hooks>>) {
+    let h = hooks.clone();
+    engine.register_fn("on_frame", move |callback: FnPtr| {
+        h.borrow_mut().frame.push(callback)
+    });
+    let h = hooks.clone();
+    engine.register_fn(
+        "on_pc",
+        move |pc: INT, callback: FnPtr| -> ScriptResult<()> {
+            h.borrow_mut().pc.push((address(pc)? as u16, callback));
+            Ok(())
+        },
+    );
+    let h = hooks.clone();
+    engine.register_fn(
+        "on_write",
+        move |at: INT, callback: FnPtr| -> ScriptResult<()> {
+            h.borrow_mut().write.push((address(at)? as u16, callback));
+            Ok(())
+        },
+    );
+    let h = hooks.clone();
+    engine.register_fn("on_sound", move |callback: FnPtr| {
+        h.borrow_mut().sound.push(callback)
+    });
+
+    let c = context.clone();
+    engine.register_fn("pc", move || c.borrow().chip8.pc() as INT);
+    let c = context.clone();
+    engine.register_fn("set_pc", move |pc: INT| -> ScriptResult<()> {
+        c.borrow_mut().chip8.set_pc(address(pc)? as u16);
+        Ok(())
+    });
+    let c = context.clone();
+    engine.register_fn("i", move || c.borrow().chip8.index() as INT);
+    let c = context.clone();
+    engine.register_fn("set_i", move |index: INT| {
+        c.borrow_mut().chip8.set_index(index as u16)
+    });
+    let c = context.clone();
+    engine.register_fn("dt", move || c.borrow().chip8.delay_timer() as INT);
+    let c = context.clone();
+    engine.register_fn("set_dt", move |value: INT| {
+        c.borrow_mut().chip8.set_delay_timer(value as u8)
+    });
+    let c = context.clone();
+    engine.register_fn("st", move || c.borrow().chip8.sound_timer() as INT);
+    let c = context.clone();
+    engine.register_fn("set_st", move |value: INT| {
+        c.borrow_mut().chip8.set_sound_timer(value as u8)
+    });
+    let c = context.clone();
+    engine.register_fn("v", move |x: INT| -> ScriptResult<INT> {
+        Ok(c.borrow().chip8.v()[register(x)?] as INT)
+    });
+    let c = context.clone();
+    engine.register_fn("set_v", move |x: INT, value: INT| -> ScriptResult<()> {
+        c.borrow_mut().chip8.set_v(register(x)?, value as u8);
+        Ok(())
+    });
+
+    let c = context.clone();
+    engine.register_fn("peek", move |at: INT| -> ScriptResult<INT> {
+        Ok(c.borrow().chip8.memory()[address(at)?] as INT)
+    });
+    let c = context.clone();
+    engine.register_fn("poke", move |at: INT, value: INT| -> ScriptResult<()> {
+        c.borrow_mut().chip8.memory_mut()[address(at)?] = value as u8;
+        Ok(())
+    });
+    let c = context.clone();
+    engine.register_fn("pixel", move |x: INT, y: INT| {
+        let (x, y) = (x as usize % SCREEN_WIDTH, y as usize % SCREEN_HEIGHT);
+        c.borrow().chip8.framebuffer()[y * SCREEN_WIDTH + x]
+    });
+
+    let c = context.clone();
+    engine.register_fn("press", move |k: INT| -> ScriptResult<()> {
+        c.borrow_mut().keys[key(k)?] = true;
+        Ok(())
+    });
+    let c = context.clone();
+    engine.register_fn("release", move |k: INT| -> ScriptResult<()> {
+        c.borrow_mut().keys[key(k)?] = false;
+        Ok(())
+    });
+    let c = context.clone();
+    engine.register_fn("frame", move || c.borrow().frame);
+
+    let c = context.clone();
+    engine.register_fn("draw_text", move |row: INT, text: &str| {
+        let overlay = &mut c.borrow_mut().overlay;
+        let row = row.max(0) as usize;
+        if overlay.len() <= row {
+            overlay.resize(row + 1, String::new());
+        }
+        overlay[row] = text.to_string();
+    });
+    let c = context.clone();
+    engine.register_fn("clear_text", move || c.borrow_mut().overlay.clear());
+    let c = context.clone();
+    engine.register_fn("screenshot", move |path: &str| -> ScriptResult<()> {
+        let rgb: Vec<u8> = c
+            .borrow()
+            .chip8
+            .framebuffer()
+            .iter()
+            .flat_map(|lit| if *lit { [0xff; 3] } else { [0; 3] })
+            .collect();
+        png::write_rgb(path, SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32, &rgb)
+            .map_err(|e| format!("can't write {}: {}", path, e).into())
+    });
+    let c = context.clone();
+    engine.register_fn("reset", move || c.borrow_mut().chip8.reset());
+    let c = context.clone();
+    engine.register_fn("quit", move || c.borrow_mut().quit = true);
+}
+
+// the hooks for the addresses from start to start + len
+fn hooks_at(hooks: &[(u16, FnPtr)], start: u16, len: usize) -> Vec<(u16, FnPtr)> {
+    hooks
+        .iter()
+        .filter(|(address, _)| (*address as usize).wrapping_sub(start as usize) < len)
+        .cloned()
+        .collect()
+}
+
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    context: Rc<RefCell<Context>>,
+    hooks: Rc<RefCell<Hooks>>,
+    started: bool,
+    // buzzer state after the last frame, the sound hooks run when it changes
+    sound: bool,
+    // the last step waited for a key, so the PC hooks don't run again for the same instruction
+    waiting: bool,
+    error: Option<String>,
+}
+
+impl Script {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let source =
+            fs::read_to_string(path).map_err(|e| format!("can't read script {}: {}", path, e))?;
+        Self::new(&source).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn new(source: &str) -> Result<Self, String> {
+        let mut engine = Engine::new();
+        let context = Rc::new(RefCell::new(Context::default()));
+        let hooks = Rc::new(RefCell::new(Hooks::default()));
+        register_api(&mut engine, &context, &hooks);
+        let ast = engine.compile(source).map_err(|e| e.to_string())?;
+        Ok(Self {
+            engine,
+            ast,
+            context,
+            hooks,
+            started: false,
+            sound: false,
+            waiting: false,
+            error: None,
+        })
+    }
+
+    // run one frame like Chip8::run_frame, calling the hooks along the way. the top level of the
+    // script runs before the first frame. instructions are stepped one at a time while there are
+    // PC or write hooks
+    pub fn run_frame(&mut self, chip8: &mut Chip8) -> Result<(), Chip8Error> {
+        if !self.started {
+            self.started = true;
+            self.with_chip8(chip8, |engine, ast| engine.run_ast(ast));
+        }
+
+        let stepping = {
+            let hooks = self.hooks.borrow();
+            self.error.is_none() && !(hooks.pc.is_empty() && hooks.write.is_empty())
+        };
+        let result = if stepping {
+            self.step_frame(chip8)
+        } else {
+            chip8.run_frame()
+        };
+
+        if chip8.sound_active() != self.sound {
+            self.sound = chip8.sound_active();
+            let hooks = self.hooks.borrow().sound.clone();
+            for hook in hooks {
+                self.call(chip8, &hook, (self.sound,));
+            }
+        }
+        self.context.borrow_mut().frame += 1;
+        let hooks = self.hooks.borrow().frame.clone();
+        for hook in hooks {
+            self.call(chip8, &hook, ());
+        }
+        result
+    }
+
+    fn step_frame(&mut self, chip8: &mut Chip8) -> Result<(), Chip8Error> {
+        for _ in 0..chip8.cycles_per_frame() {
+            let pc = chip8.pc();
+            if !self.waiting {
+                let hooks = hooks_at(&self.hooks.borrow().pc, pc, 1);
+                for (_, hook) in hooks {
+                    self.call(chip8, &hook, (pc as INT,));
+                }
+            }
+            self.waiting = chip8.step()? == Step::WaitingForKey;
+            if let Some((start, len)) = chip8.last_write() {
+                let hooks = hooks_at(&self.hooks.borrow().write, start, len);
+                for (address, hook) in hooks {
+                    let value = chip8.memory()[address as usize & 0xfff];
+                    self.call(chip8, &hook, (address as INT, value as INT));
+                }
+            }
+        }
+        chip8.tick_timers();
+        Ok(())
+    }
+
+    fn call(&mut self, chip8: &mut Chip8, hook: &FnPtr, args: impl FuncArgs) {
+        self.with_chip8(chip8, |engine, ast| {
+            hook.call::<Dynamic>(engine, ast, args).map(|_| ())
+        });
+    }
+
+    // run script code with the interpreter moved into the context, unless the script already
+    // failed
+    fn with_chip8(
+        &mut self,
+        chip8: &mut Chip8,
+        run: impl FnOnce(&Engine, &AST) -> ScriptResult<()>,
+    ) {
+        if self.error.is_some() {
+            return;
+        }
+        std::mem::swap(chip8, &mut self.context.borrow_mut().chip8);
+        let result = run(&self.engine, &self.ast);
+        std::mem::swap(chip8, &mut self.context.borrow_mut().chip8);
+        if let Err(e) = result {
+            self.error = Some(e.to_string());
+        }
+    }
+
+    // keys held by press, for the host to add to its own
+    pub fn is_key_pressed(&self, key: usize) -> bool {
+        self.context.borrow().keys[key]
+    }
+
+    // lines drawn with draw_text, top to bottom
+    pub fn overlay(&self) -> Vec<String> {
+        self.context.borrow().overlay.clone()
+    }
+
+    pub fn quit_requested(&self) -> bool {
+        self.context.borrow().quit
+    }
+
+    // the error that stopped the script, if any
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
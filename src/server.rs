@@ -0,0 +1,622 @@
+// JSON-RPC 2.0 control server, for driving a running interpreter from scripts. requests and
+// responses are one JSON object per line, over TCP or a unix socket. connections are read on
+// their own threads, the requests are handled by the host loop in poll so they never race the
+// machine
+//
+// methods:
+//   load_rom {data: base64}  reset
+//   pause   resume   status
+//   step {count}            run instructions, returns the registers
+//   step_frame {count}      run whole frames, returns the registers
+//   get_registers           set_registers {pc, i, dt, st, v0..vf}
+//   read_memory {address, length} -> {address, data: base64}
+//   write_memory {address, data: base64}
+//   press_key {key}         release_key {key}
+//   get_framebuffer -> {width, height, data: base64, a byte per pixel, 1 when lit}
+//   subscribe {events: ["frame", "sound", "key"]}   unsubscribe
+//
+// subscribers get "frame" notifications {frame, pc, drawn} after every emulated frame, "sound"
+// notifications {frame, active} when the buzzer starts and stops and "key" notifications
+// {frame, key, pressed} when the host's own keyboard or pads press or release a key
+//
+// there is no authentication, anyone who can connect can read and rewrite the machine, so bind
+// only takes loopback addresses and other hosts need an explicit bind_remote
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::json::Json;
+use crate::{base64, Chip8, KeyEvent, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+// standard JSON-RPC error codes, and one for everything the interpreter refused
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const EMULATOR_ERROR: i64 = -32000;
+
+// what a single step or step_frame call may run, so a typo can't hang the host
+const MAX_STEPS: u64 = 1_000_000;
+const MAX_FRAMES: u64 = 3600;
+// longest request line, a whole memory dump in base64 fits many times over
+const MAX_REQUEST_LEN: u64 = 64 * 1024;
+
+type Writer = Arc<Mutex<Box<dyn Write + Send>>>;
+
+enum Incoming {
+    Request {
+        connection: u64,
+        writer: Writer,
+        line: String,
+    },
+    Closed(u64),
+}
+
+struct Subscription {
+    connection: u64,
+    writer: Writer,
+    frame: bool,
+    sound: bool,
+    key: bool,
+}
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    fn params(message: impl Into<String>) -> Self {
+        Self::new(INVALID_PARAMS, message)
+    }
+}
+
+pub struct Server {
+    address: String,
+    incoming: Receiver<Incoming>,
+    subscriptions: Vec<Subscription>,
+    // keys held down by clients, the host ORs them with its own input
+    keys: [bool; 16],
+    // emulated frames since the server started
+    frame: u64,
+    was_beeping: bool,
+}
+
+impl Server {
+    // listen on a loopback TCP address like 127.0.0.1:6502, or on a unix socket with unix:PATH
+    pub fn bind(address: &str) -> io::Result<Self> {
+        Self::listen(address, false)
+    }
+
+    // like bind, but also listens on addresses other hosts can reach
+    pub fn bind_remote(address: &str) -> io::Result<Self> {
+        Self::listen(address, true)
+    }
+
+    fn listen(address: &str, remote: bool) -> io::Result<Self> {
+        let (sender, incoming) = mpsc::channel();
+        let address = match address.strip_prefix("unix:") {
+            Some(path) => listen_unix(path, sender)?,
+            None => {
+                let addresses: Vec<SocketAddr> = address.to_socket_addrs()?.collect();
+                if !remote && !addresses.iter().all(|address| address.ip().is_loopback()) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::PermissionDenied,
+                        format!("{} isn't a loopback address", address),
+                    ));
+                }
+                let listener = TcpListener::bind(&addresses[..])?;
+                let local = listener.local_addr()?.to_string();
+                thread::spawn(move || {
+                    for (connection, stream) in (0..).zip(listener.incoming()) {
+                        let Ok(stream) = stream else {
+                            continue;
+                        };
+                        if let Ok(writer) = stream.try_clone() {
+                            let reader = BufReader::new(stream);
+                            serve(connection, reader, Box::new(writer), sender.clone());
+                        }
+                    }
+                });
+                local
+            }
+        };
+        Ok(Self {
+            address,
+            incoming,
+            subscriptions: Vec::new(),
+            keys: [false; 16],
+            frame: 0,
+            was_beeping: false,
+        })
+    }
+
+    // where the server ended up listening, with the real port when bound to port 0
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    pub fn is_key_pressed(&self, key: usize) -> bool {
+        self.keys[key & 0xf]
+    }
+
+    // handle every request that came in since the last call. called once per host frame, also
+    // while paused. pause and resume flip paused
+    pub fn poll(&mut self, chip8: &mut Chip8, paused: &mut bool) {
+        while let Ok(incoming) = self.incoming.try_recv() {
+            match incoming {
+                Incoming::Closed(connection) => self
+                    .subscriptions
+                    .retain(|subscription| subscription.connection != connection),
+                Incoming::Request {
+                    connection,
+                    writer,
+                    line,
+                } => {
+                    let response = match Json::parse(&line) {
+                        Ok(Json::Array(requests)) if !requests.is_empty() => {
+                            let responses: Vec<Json> = requests
+                                .iter()
+                                .filter_map(|request| {
+                                    self.handle(connection, &writer, request, chip8, paused)
+                                })
+                                .collect();
+                            (!responses.is_empty()).then_some(Json::Array(responses))
+                        }
+                        Ok(request) => self.handle(connection, &writer, &request, chip8, paused),
+                        Err(e) => Some(error_response(
+                            Json::Null,
+                            RpcError::new(PARSE_ERROR, format!("parse error: {}", e)),
+                        )),
+                    };
+                    if let Some(response) = response {
+                        // a client that went away is noticed by its reader thread
+                        let _ = send(&writer, &response);
+                    }
+                }
+            }
+        }
+    }
+
+    // tell subscribers a frame ran. call after every emulated frame, before clearing the drawflag
+    pub fn end_frame(&mut self, chip8: &Chip8) {
+        self.frame += 1;
+        let frame = notification(
+            "frame",
+            Json::object(vec![
+                ("frame", self.frame.into()),
+                ("pc", (chip8.pc() as u64).into()),
+                ("drawn", chip8.is_drawflag_set().into()),
+            ]),
+        );
+        let beeping = chip8.sound_active();
+        let sound = (beeping != self.was_beeping).then(|| {
+            notification(
+                "sound",
+                Json::object(vec![
+                    ("frame", self.frame.into()),
+                    ("active", beeping.into()),
+                ]),
+            )
+        });
+        self.was_beeping = beeping;
+
+        self.subscriptions.retain(|subscription| {
+            let mut ok = true;
+            if subscription.frame {
+                ok &= send(&subscription.writer, &frame).is_ok();
+            }
+            if let (true, Some(sound)) = (subscription.sound, &sound) {
+                ok &= send(&subscription.writer, sound).is_ok();
+            }
+            ok
+        });
+    }
+
+    // tell subscribers about a key the host pressed or released, see Keypad::pop_key_event
+    pub fn key_event(&mut self, event: KeyEvent) {
+        let (key, pressed) = match event {
+            KeyEvent::Pressed(key) => (key, true),
+            KeyEvent::Released(key) => (key, false),
+        };
+        let key = notification(
+            "key",
+            Json::object(vec![
+                ("frame", self.frame.into()),
+                ("key", (key as u64).into()),
+                ("pressed", pressed.into()),
+            ]),
+        );
+        self.subscriptions
+            .retain(|subscription| !subscription.key || send(&subscription.writer, &key).is_ok());
+    }
+
+    // the response to one request, None for notifications
+    fn handle(
+        &mut self,
+        connection: u64,
+        writer: &Writer,
+        request: &Json,
+        chip8: &mut Chip8,
+        paused: &mut bool,
+    ) -> Option<Json> {
+        let id = request.get("id").cloned();
+        let Some(method) = request.get("method").and_then(Json::as_str) else {
+            return Some(error_response(
+                id.unwrap_or(Json::Null),
+                RpcError::new(INVALID_REQUEST, "invalid request"),
+            ));
+        };
+        let params = request.get("params").cloned().unwrap_or(Json::Null);
+        let result = self.call(connection, writer, method, &params, chip8, paused);
+        let id = id?;
+        Some(match result {
+            Ok(result) => Json::object(vec![
+                ("jsonrpc", "2.0".into()),
+                ("result", result),
+                ("id", id),
+            ]),
+            Err(error) => error_response(id, error),
+        })
+    }
+
+    fn call(
+        &mut self,
+        connection: u64,
+        writer: &Writer,
+        method: &str,
+        params: &Json,
+        chip8: &mut Chip8,
+        paused: &mut bool,
+    ) -> Result<Json, RpcError> {
+        match method {
+            "load_rom" => {
+                let data = param(params, "data").ok_or(RpcError::params("data is required"))?;
+                let rom = bytes(data)?;
+                chip8.load_rom(&rom).map_err(emulator_error)?;
+                self.keys = [false; 16];
+                Ok(Json::Null)
+            }
+            "reset" => {
+                chip8.reset();
+                self.keys = [false; 16];
+                Ok(Json::Null)
+            }
+            "pause" => {
+                *paused = true;
+                Ok(Json::Null)
+            }
+            "resume" => {
+                *paused = false;
+                Ok(Json::Null)
+            }
+            "status" => Ok(Json::object(vec![
+                ("paused", (*paused).into()),
+                ("halted", chip8.is_halted().into()),
+                (
+                    "halt_reason",
+                    chip8.halt_reason().map(|e| e.to_string()).into(),
+                ),
+                ("frame", self.frame.into()),
+                ("sound", chip8.sound_active().into()),
+            ])),
+            "step" => {
+                for _ in 0..count(params, MAX_STEPS)? {
+                    chip8.step().map_err(emulator_error)?;
+                }
+                Ok(registers(chip8))
+            }
+            "step_frame" => {
+                for _ in 0..count(params, MAX_FRAMES)? {
+                    let result = chip8.run_frame();
+                    self.end_frame(chip8);
+                    result.map_err(emulator_error)?;
+                }
+                Ok(registers(chip8))
+            }
+            "get_registers" => Ok(registers(chip8)),
+            "set_registers" => {
+                let entries = params
+                    .as_object()
+                    .ok_or(RpcError::params("registers are an object"))?;
+                // check everything first so a bad value doesn't leave half the registers set
+                let mut writes = Vec::new();
+                for (name, value) in entries {
+                    let name = name.to_ascii_lowercase();
+                    let max = match name.as_str() {
+                        // addresses, memory is 4 KiB
+                        "pc" | "i" => 0xfff,
+                        "dt" | "st" => 0xff,
+                        _ => match register_index(&name) {
+                            Some(_) => 0xff,
+                            None => return Err(RpcError::params(format!("no register {}", name))),
+                        },
+                    };
+                    let value = value
+                        .as_u64()
+                        .filter(|value| *value <= max)
+                        .ok_or_else(|| RpcError::params(format!("{} is out of range", name)))?;
+                    writes.push((name, value));
+                }
+                for (name, value) in writes {
+                    match name.as_str() {
+                        "pc" => chip8.set_pc(value as u16),
+                        "i" => chip8.set_index(value as u16),
+                        "dt" => chip8.set_delay_timer(value as u8),
+                        "st" => chip8.set_sound_timer(value as u8),
+                        _ => {
+                            if let Some(x) = register_index(&name) {
+                                chip8.set_v(x, value as u8);
+                            }
+                        }
+                    }
+                }
+                Ok(registers(chip8))
+            }
+            "read_memory" => {
+                let address = number(params, "address")? as usize;
+                let length = match param(params, "length") {
+                    Some(length) => length
+                        .as_u64()
+                        .ok_or(RpcError::params("length is a number"))?
+                        as usize,
+                    None => 1,
+                };
+                let memory = chip8.memory();
+                let end = address
+                    .checked_add(length)
+                    .filter(|end| *end <= memory.len())
+                    .ok_or(RpcError::params("outside of memory"))?;
+                Ok(Json::object(vec![
+                    ("address", (address as u64).into()),
+                    ("data", base64::encode(&memory[address..end]).into()),
+                ]))
+            }
+            "write_memory" => {
+                let address = number(params, "address")? as usize;
+                let data =
+                    bytes(param(params, "data").ok_or(RpcError::params("data is required"))?)?;
+                let memory = chip8.memory_mut();
+                let end = address
+                    .checked_add(data.len())
+                    .filter(|end| *end <= memory.len())
+                    .ok_or(RpcError::params("outside of memory"))?;
+                memory[address..end].copy_from_slice(&data);
+                Ok(Json::Null)
+            }
+            "press_key" | "release_key" => {
+                let key = number(params, "key")?;
+                if key > 0xf {
+                    return Err(RpcError::params("keys go from 0 to 15"));
+                }
+                let pressed = method == "press_key";
+                self.keys[key as usize] = pressed;
+                chip8.set_key(key as usize, pressed);
+                Ok(Json::Null)
+            }
+            "get_framebuffer" => {
+                let pixels: Vec<u8> = chip8.framebuffer().iter().map(|lit| *lit as u8).collect();
+                Ok(Json::object(vec![
+                    ("width", (SCREEN_WIDTH as u64).into()),
+                    ("height", (SCREEN_HEIGHT as u64).into()),
+                    ("data", base64::encode(&pixels).into()),
+                ]))
+            }
+            "subscribe" => {
+                let (mut frame, mut sound, mut key) = (true, true, true);
+                if let Some(events) = param(params, "events") {
+                    let events = events
+                        .as_array()
+                        .ok_or(RpcError::params("events is an array"))?;
+                    (frame, sound, key) = (false, false, false);
+                    for event in events {
+                        match event.as_str() {
+                            Some("frame") => frame = true,
+                            Some("sound") => sound = true,
+                            Some("key") => key = true,
+                            _ => return Err(RpcError::params("events are frame, sound and key")),
+                        }
+                    }
+                }
+                self.subscriptions
+                    .retain(|subscription| subscription.connection != connection);
+                self.subscriptions.push(Subscription {
+                    connection,
+                    writer: writer.clone(),
+                    frame,
+                    sound,
+                    key,
+                });
+                Ok(Json::Null)
+            }
+            "unsubscribe" => {
+                self.subscriptions
+                    .retain(|subscription| subscription.connection != connection);
+                Ok(Json::Null)
+            }
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("no method {}", method),
+            )),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn listen_unix(path: &str, sender: Sender<Incoming>) -> io::Result<String> {
+    use std::os::unix::fs::FileTypeExt;
+    use std::os::unix::net::UnixListener;
+
+    // a socket left behind by an earlier run, anything else at that path is left alone
+    if std::fs::metadata(path).is_ok_and(|meta| meta.file_type().is_socket()) {
+        std::fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    thread::spawn(move || {
+        for (connection, stream) in (0..).zip(listener.incoming()) {
+            let Ok(stream) = stream else {
+                continue;
+            };
+            if let Ok(writer) = stream.try_clone() {
+                serve(
+                    connection,
+                    BufReader::new(stream),
+                    Box::new(writer),
+                    sender.clone(),
+                );
+            }
+        }
+    });
+    Ok(format!("unix:{}", path))
+}
+
+#[cfg(not(unix))]
+fn listen_unix(_path: &str, _sender: Sender<Incoming>) -> io::Result<String> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "unix sockets need a unix system",
+    ))
+}
+
+// read requests off a connection on its own thread and queue them for poll
+fn serve(
+    connection: u64,
+    reader: impl BufRead + Send + 'static,
+    writer: Box<dyn Write + Send>,
+    sender: Sender<Incoming>,
+) {
+    let writer = Arc::new(Mutex::new(writer));
+    thread::spawn(move || {
+        let mut reader = reader;
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            match (&mut reader)
+                .take(MAX_REQUEST_LEN + 1)
+                .read_until(b'\n', &mut line)
+            {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+            // a line cut short by take, answer it and throw the rest of it away
+            if line.len() as u64 > MAX_REQUEST_LEN && !line.ends_with(b"\n") {
+                let error = RpcError::new(
+                    INVALID_REQUEST,
+                    format!("requests are at most {} bytes", MAX_REQUEST_LEN),
+                );
+                if send(&writer, &error_response(Json::Null, error)).is_err()
+                    || !matches!(reader.skip_until(b'\n'), Ok(n) if n > 0)
+                {
+                    break;
+                }
+                continue;
+            }
+            let line = String::from_utf8_lossy(&line);
+            if line.trim().is_empty() {
+                continue;
+            }
+            let request = Incoming::Request {
+                connection,
+                writer: writer.clone(),
+                line: line.into_owned(),
+            };
+            if sender.send(request).is_err() {
+                return;
+            }
+        }
+        let _ = sender.send(Incoming::Closed(connection));
+    });
+}
+
+fn send(writer: &Writer, message: &Json) -> io::Result<()> {
+    let mut writer = writer.lock().unwrap_or_else(|e| e.into_inner());
+    writeln!(writer, "{}", message)?;
+    writer.flush()
+}
+
+fn notification(method: &str, params: Json) -> Json {
+    Json::object(vec![
+        ("jsonrpc", "2.0".into()),
+        ("method", method.into()),
+        ("params", params),
+    ])
+}
+
+fn error_response(id: Json, error: RpcError) -> Json {
+    Json::object(vec![
+        ("jsonrpc", "2.0".into()),
+        (
+            "error",
+            Json::object(vec![
+                ("code", Json::Number(error.code as f64)),
+                ("message", error.message.into()),
+            ]),
+        ),
+        ("id", id),
+    ])
+}
+
+fn emulator_error(error: crate::Chip8Error) -> RpcError {
+    RpcError::new(EMULATOR_ERROR, error.to_string())
+}
+
+fn param<'a>(params: &'a Json, name: &str) -> Option<&'a Json> {
+    params.get(name).filter(|value| **value != Json::Null)
+}
+
+fn number(params: &Json, name: &str) -> Result<u64, RpcError> {
+    param(params, name)
+        .and_then(Json::as_u64)
+        .ok_or_else(|| RpcError::params(format!("{} is a number", name)))
+}
+
+fn count(params: &Json, max: u64) -> Result<u64, RpcError> {
+    let count = match param(params, "count") {
+        Some(_) => number(params, "count")?,
+        None => 1,
+    };
+    if count > max {
+        return Err(RpcError::params(format!("count is at most {}", max)));
+    }
+    Ok(count)
+}
+
+fn bytes(data: &Json) -> Result<Vec<u8>, RpcError> {
+    let data = data
+        .as_str()
+        .ok_or(RpcError::params("data is a base64 string"))?;
+    base64::decode(data).map_err(RpcError::params)
+}
+
+// v0 to vf
+fn register_index(name: &str) -> Option<usize> {
+    let digit = name.strip_prefix('v')?;
+    if digit.len() != 1 {
+        return None;
+    }
+    usize::from_str_radix(digit, 16).ok()
+}
+
+fn registers(chip8: &Chip8) -> Json {
+    let v: Vec<u64> = chip8.v().iter().map(|v| *v as u64).collect();
+    let stack: Vec<u64> = chip8.stack().iter().map(|a| *a as u64).collect();
+    Json::object(vec![
+        ("pc", (chip8.pc() as u64).into()),
+        ("i", (chip8.index() as u64).into()),
+        ("sp", (chip8.sp() as u64).into()),
+        ("v", v.into()),
+        ("dt", (chip8.delay_timer() as u64).into()),
+        ("st", (chip8.sound_timer() as u64).into()),
+        ("stack", stack.into()),
+        ("halted", chip8.is_halted().into()),
+    ])
+}
@@ -0,0 +1,170 @@
+use crate::{Chip8, Chip8Error, MAX_STACK_DEPTH, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+// save states are a flat byte dump of the machine: cpu, timers, XO-CHIP audio, the fault that
+// halted it, stack, memory and the screen packed 8 pixels per byte
+const STATE_MAGIC: &[u8; 4] = b"C8ST";
+const STATE_VERSION: u8 = 2;
+const SCREEN_BYTES: usize = SCREEN_WIDTH * SCREEN_HEIGHT / 8;
+
+// reads a save state front to back
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        if self.data.len() < len {
+            return Err("save state is truncated".to_string());
+        }
+        let (head, tail) = self.data.split_at(len);
+        self.data = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+}
+
+// a kind byte and four bytes of details, the kind is 0 when the machine isn't halted
+fn write_halt(state: &mut Vec<u8>, halted: Option<&Chip8Error>) {
+    let (kind, address, opcode) = match halted {
+        None => (0, 0, 0),
+        Some(Chip8Error::UnknownOpcode { address, opcode }) => (1, *address, *opcode),
+        Some(Chip8Error::MachineCode { address, opcode }) => (2, *address, *opcode),
+        Some(Chip8Error::PcOutOfBounds { address }) => (3, *address, 0),
+        Some(Chip8Error::StackOverflow { address }) => (4, *address, 0),
+        Some(Chip8Error::StackUnderflow { address }) => (5, *address, 0),
+        // load_rom refuses the ROM before anything runs, it never halts a machine
+        Some(Chip8Error::RomTooLarge { .. }) => (0, 0, 0),
+    };
+    state.push(kind);
+    state.extend_from_slice(&address.to_le_bytes());
+    state.extend_from_slice(&opcode.to_le_bytes());
+}
+
+fn read_halt(reader: &mut Reader) -> Result<Option<Chip8Error>, String> {
+    let kind = reader.u8()?;
+    let address = reader.u16()?;
+    let opcode = reader.u16()?;
+    Ok(match kind {
+        0 => None,
+        1 => Some(Chip8Error::UnknownOpcode { address, opcode }),
+        2 => Some(Chip8Error::MachineCode { address, opcode }),
+        3 => Some(Chip8Error::PcOutOfBounds { address }),
+        4 => Some(Chip8Error::StackOverflow { address }),
+        5 => Some(Chip8Error::StackUnderflow { address }),
+        _ => return Err("save state has a bad halt reason".to_string()),
+    })
+}
+
+impl Chip8 {
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut state = Vec::new();
+        state.extend_from_slice(STATE_MAGIC);
+        state.push(STATE_VERSION);
+
+        state.extend_from_slice(&self.cpu.pc.to_le_bytes());
+        state.extend_from_slice(&self.cpu.index.to_le_bytes());
+        state.extend_from_slice(&self.cpu.sp.to_le_bytes());
+        state.extend_from_slice(&self.cpu.v);
+        state.extend_from_slice(&self.cpu.rpl);
+        // 0xff when FX0A is not waiting on a key
+        state.push(self.cpu.key_wait.map_or(0xff, |key| key as u8));
+
+        state.push(self.timers.delay);
+        state.push(self.timers.sound);
+
+        // a flag byte, then the 16 pattern bytes whether there is a pattern or not
+        state.push(self.audio_pattern.is_some() as u8);
+        state.extend_from_slice(&self.audio_pattern.unwrap_or([0x0; 16]));
+        state.push(self.audio_pitch);
+
+        write_halt(&mut state, self.halted.as_ref());
+
+        for address in self.memory.stack.iter() {
+            state.extend_from_slice(&address.to_le_bytes());
+        }
+        state.extend_from_slice(&self.memory.memory);
+
+        let mut screen = [0x0u8; SCREEN_BYTES];
+        for (i, pixel) in self.screen.iter().enumerate() {
+            if *pixel {
+                screen[i / 8] |= 0b1000_0000 >> (i % 8);
+            }
+        }
+        state.extend_from_slice(&screen);
+        state
+    }
+
+    // restore a state made by save_state. nothing is changed if the state can't be read
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), String> {
+        let mut reader = Reader { data: state };
+        if reader.take(4)? != STATE_MAGIC {
+            return Err("not a chip8 save state".to_string());
+        }
+        let version = reader.u8()?;
+        if version != STATE_VERSION {
+            return Err(format!("unsupported save state version {}", version));
+        }
+
+        let pc = reader.u16()?;
+        let index = reader.u16()?;
+        let sp = reader.u16()?;
+        let v = reader.take(16)?;
+        let rpl = reader.take(8)?;
+        let key_wait = match reader.u8()? {
+            0xff => None,
+            key => Some(key as usize & 0xf),
+        };
+        let delay = reader.u8()?;
+        let sound = reader.u8()?;
+        let has_pattern = reader.u8()? != 0;
+        let pattern = reader.take(16)?;
+        let audio_pattern = if has_pattern {
+            let mut bytes = [0x0u8; 16];
+            bytes.copy_from_slice(pattern);
+            Some(bytes)
+        } else {
+            None
+        };
+        let audio_pitch = reader.u8()?;
+        let halted = read_halt(&mut reader)?;
+        let mut stack = [0x0u16; MAX_STACK_DEPTH];
+        for address in stack.iter_mut() {
+            *address = reader.u16()?;
+        }
+        let memory = reader.take(4096)?;
+        let screen = reader.take(SCREEN_BYTES)?;
+        // deeper than the calls this machine allows, wherever it keeps its stack
+        if sp as usize > self.stack_depth {
+            return Err("save state has a bad stack pointer".to_string());
+        }
+
+        self.cpu.pc = pc;
+        self.cpu.index = index;
+        self.cpu.sp = sp;
+        self.cpu.v.copy_from_slice(v);
+        self.cpu.rpl.copy_from_slice(rpl);
+        self.cpu.key_wait = key_wait;
+        self.timers.delay = delay;
+        self.timers.sound = sound;
+        self.audio_pattern = audio_pattern;
+        self.audio_pitch = audio_pitch;
+        self.halted = halted;
+        self.memory.stack = stack;
+        self.memory.memory.copy_from_slice(memory);
+
+        let pixels: Vec<bool> = (0..SCREEN_WIDTH * SCREEN_HEIGHT)
+            .map(|i| screen[i / 8] & (0b1000_0000 >> (i % 8)) != 0)
+            .collect();
+        self.screen.copy_from_slice(&pixels);
+        self.draw = true;
+        Ok(())
+    }
+}
@@ -0,0 +1,266 @@
+pub const SAMPLE_RATE: i32 = 44100;
+
+// the timers tick at 60Hz, the headless backend renders this many samples per tick
+pub const SAMPLES_PER_FRAME: usize = SAMPLE_RATE as usize / 60;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Waveform {
+    Square,
+    Sine,
+    Triangle,
+    Noise,
+}
+
+impl Waveform {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "square" => Some(Waveform::Square),
+            "sine" => Some(Waveform::Sine),
+            "triangle" => Some(Waveform::Triangle),
+            "noise" => Some(Waveform::Noise),
+            _ => None,
+        }
+    }
+}
+
+// what the buzzer sounds like. attack and release are in seconds, a few milliseconds is enough
+// to get rid of the click when the tone starts and stops
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Tone {
+    pub frequency: f32,
+    pub volume: f32,
+    pub waveform: Waveform,
+    pub attack: f32,
+    pub release: f32,
+}
+
+impl Default for Tone {
+    fn default() -> Self {
+        Self {
+            frequency: 440.0,
+            volume: 0.25,
+            waveform: Waveform::Square,
+            attack: 0.005,
+            release: 0.005,
+        }
+    }
+}
+
+pub struct SquareWave {
+    phase_inc: f32,
+    phase: f32,
+    tone: Tone,
+    sample_rate: i32,
+    // true while the sound timer is running
+    gate: bool,
+    // envelope level, ramps up to 1 while the gate is open and back down to 0 after
+    level: f32,
+    // 16-bit lfsr for the noise waveform, stepped once per period
+    lfsr: u16,
+}
+
+impl SquareWave {
+    pub fn new(tone: Tone, sample_rate: i32) -> Self {
+        Self {
+            phase_inc: tone.frequency / sample_rate as f32,
+            phase: 0.0,
+            tone,
+            sample_rate,
+            gate: false,
+            level: 0.0,
+            lfsr: 0xace1,
+        }
+    }
+
+    pub fn set_tone(&mut self, tone: Tone) {
+        self.phase_inc = tone.frequency / self.sample_rate as f32;
+        self.tone = tone;
+    }
+
+    pub fn set_gate(&mut self, gate: bool) {
+        self.gate = gate;
+    }
+
+    // true once the release has faded out and only silence is left
+    pub fn is_silent(&self) -> bool {
+        !self.gate && (self.level == 0.0 || self.tone.release <= 0.0)
+    }
+
+    // per sample change of the envelope level for a ramp of the given length
+    fn envelope_step(&self, seconds: f32) -> f32 {
+        if seconds <= 0.0 {
+            1.0
+        } else {
+            1.0 / (seconds * self.sample_rate as f32)
+        }
+    }
+
+    fn next_sample(&mut self) -> f32 {
+        if self.gate {
+            self.level = (self.level + self.envelope_step(self.tone.attack)).min(1.0);
+        } else {
+            self.level = (self.level - self.envelope_step(self.tone.release)).max(0.0);
+        }
+
+        let wave = match self.tone.waveform {
+            Waveform::Square => {
+                if self.phase <= 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Waveform::Sine => (self.phase * 2.0 * std::f32::consts::PI).sin(),
+            Waveform::Triangle => 1.0 - 4.0 * (self.phase - 0.5).abs(),
+            Waveform::Noise => {
+                if self.lfsr & 1 == 1 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+        };
+
+        let phase = self.phase + self.phase_inc;
+        if phase >= 1.0 && self.tone.waveform == Waveform::Noise {
+            let bit = (self.lfsr ^ (self.lfsr >> 2) ^ (self.lfsr >> 3) ^ (self.lfsr >> 5)) & 1;
+            self.lfsr = (self.lfsr >> 1) | (bit << 15);
+        }
+        self.phase = phase % 1.0;
+
+        wave * self.tone.volume * self.level
+    }
+
+    pub fn fill(&mut self, out: &mut [f32]) {
+        for x in out.iter_mut() {
+            *x = self.next_sample();
+        }
+    }
+}
+
+// XO-CHIP audio: a 128 bit, 1-bit-per-sample pattern looped at a rate set by the pitch register
+pub struct SamplePattern {
+    pattern: [u8; 16],
+    pitch: u8,
+    // position in the pattern, in bits
+    position: f32,
+    position_inc: f32,
+    sample_rate: i32,
+    volume: f32,
+    gate: bool,
+}
+
+impl SamplePattern {
+    pub fn new(volume: f32, sample_rate: i32) -> Self {
+        let mut pattern = Self {
+            pattern: [0x0; 16],
+            pitch: 64,
+            position: 0.0,
+            position_inc: 0.0,
+            sample_rate,
+            volume,
+            gate: false,
+        };
+        pattern.set_pitch(64);
+        pattern
+    }
+
+    pub fn set_pattern(&mut self, pattern: [u8; 16]) {
+        self.pattern = pattern;
+    }
+
+    // playback rate in bits per second is 4000 * 2 ^ ((pitch - 64) / 48), so the default pitch
+    // of 64 plays the pattern at 4000Hz
+    pub fn set_pitch(&mut self, pitch: u8) {
+        self.pitch = pitch;
+        let rate = 4000.0 * 2f32.powf((pitch as f32 - 64.0) / 48.0);
+        self.position_inc = rate / self.sample_rate as f32;
+    }
+
+    pub fn pitch(&self) -> u8 {
+        self.pitch
+    }
+
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume;
+    }
+
+    pub fn set_gate(&mut self, gate: bool) {
+        self.gate = gate;
+    }
+
+    fn next_sample(&mut self) -> f32 {
+        if !self.gate {
+            return 0.0;
+        }
+        let bit = self.position as usize;
+        let sample = if self.pattern[bit / 8] & (0b1000_0000 >> (bit % 8)) != 0 {
+            self.volume
+        } else {
+            -self.volume
+        };
+        self.position = (self.position + self.position_inc) % 128.0;
+        sample
+    }
+
+    pub fn fill(&mut self, out: &mut [f32]) {
+        for x in out.iter_mut() {
+            *x = self.next_sample();
+        }
+    }
+}
+
+// what the audio backends play: the configurable tone, or an XO-CHIP pattern once the ROM loaded one
+pub struct Buzzer {
+    wave: SquareWave,
+    pattern: SamplePattern,
+    use_pattern: bool,
+}
+
+impl Buzzer {
+    pub fn new(tone: Tone, sample_rate: i32) -> Self {
+        Self {
+            wave: SquareWave::new(tone, sample_rate),
+            pattern: SamplePattern::new(tone.volume, sample_rate),
+            use_pattern: false,
+        }
+    }
+
+    pub fn set_tone(&mut self, tone: Tone) {
+        self.wave.set_tone(tone);
+        self.pattern.set_volume(tone.volume);
+    }
+
+    pub fn set_gate(&mut self, gate: bool) {
+        self.wave.set_gate(gate);
+        self.pattern.set_gate(gate);
+    }
+
+    pub fn is_silent(&self) -> bool {
+        if self.use_pattern {
+            !self.pattern.gate
+        } else {
+            self.wave.is_silent()
+        }
+    }
+
+    // None goes back to the tone, e.g. after a reset or when a ROM without F002 is loaded
+    pub fn set_pattern(&mut self, pattern: Option<[u8; 16]>) {
+        if let Some(pattern) = pattern {
+            self.pattern.set_pattern(pattern);
+        }
+        self.use_pattern = pattern.is_some();
+    }
+
+    pub fn set_pitch(&mut self, pitch: u8) {
+        self.pattern.set_pitch(pitch);
+    }
+
+    pub fn fill(&mut self, out: &mut [f32]) {
+        if self.use_pattern {
+            self.pattern.fill(out);
+        } else {
+            self.wave.fill(out);
+        }
+    }
+}
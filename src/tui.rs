@@ -0,0 +1,427 @@
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+use crossterm::event::{
+    self, Event, KeyCode, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::{cursor, execute, queue, style, terminal};
+
+use crate::cheats::{Cheat, Code, Mode, Target};
+use crate::ramsearch::{RamSearch, Relation, Watchpoint};
+use crate::{romdb, Chip8, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+const FRAME_DURATION: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+// most terminals only report key presses and repeats, never releases. a key counts as held for
+// this long after its last press or repeat
+const KEY_HOLD: Duration = Duration::from_millis(150);
+
+// the panel next to the screen is redrawn every this many frames even if the screen didn't change
+const PANEL_INTERVAL: u32 = 4;
+
+// RAM search candidates listed in the panel, the count is always shown
+const RESULTS_SHOWN: usize = 8;
+
+// the chip8 keypad on the left of a qwerty keyboard, the same layout as the SDL frontend
+const KEYS: [(char, usize); 16] = [
+    ('1', 0x1),
+    ('2', 0x2),
+    ('3', 0x3),
+    ('4', 0xc),
+    ('q', 0x4),
+    ('w', 0x5),
+    ('e', 0x6),
+    ('r', 0xd),
+    ('a', 0x7),
+    ('s', 0x8),
+    ('d', 0x9),
+    ('f', 0xe),
+    ('z', 0xa),
+    ('x', 0x0),
+    ('c', 0xb),
+    ('v', 0xf),
+];
+
+// the screen as text, two pixel rows per character with upper and lower half blocks
+fn half_blocks(pixels: &[bool], width: usize, height: usize) -> Vec<String> {
+    (0..height)
+        .step_by(2)
+        .map(|y| {
+            (0..width)
+                .map(|x| {
+                    let top = pixels[y * width + x];
+                    let bottom = y + 1 < height && pixels[(y + 1) * width + x];
+                    match (top, bottom) {
+                        (true, true) => '█',
+                        (true, false) => '▀',
+                        (false, true) => '▄',
+                        (false, false) => ' ',
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// RAM search, watchpoints and cheats, driven by commands typed after ':'
+#[derive(Default)]
+struct Debugger {
+    search: Option<RamSearch>,
+    watchpoints: Vec<Watchpoint>,
+    // frozen bytes, written before every frame
+    cheats: Vec<Cheat>,
+    // the command being typed, the game is paused meanwhile
+    prompt: Option<String>,
+}
+
+impl Debugger {
+    // a key pressed while the prompt is open. returns the outcome once a command ran
+    fn type_key(&mut self, chip8: &mut Chip8, code: KeyCode) -> Option<String> {
+        let prompt = self.prompt.as_mut()?;
+        match code {
+            KeyCode::Enter => {
+                let command = self.prompt.take()?;
+                Some(self.run(chip8, &command).unwrap_or_else(|e| e))
+            }
+            KeyCode::Esc => {
+                self.prompt = None;
+                None
+            }
+            KeyCode::Backspace => {
+                prompt.pop();
+                None
+            }
+            KeyCode::Char(c) => {
+                prompt.push(c);
+                None
+            }
+            _ => None,
+        }
+    }
+
+    // numbers are hex, like everywhere in the panel:
+    //  new            start a search with every address as a candidate
+    //  = != + -       keep the candidates that are equal, changed, increased or decreased since
+    //                 the last pass
+    //  = N            keep the candidates that are N
+    //  watch ADDR     pause when ADDR changes, unwatch ADDR to stop
+    //  cheat ADDR [N] freeze ADDR at N or its current value, uncheat ADDR to stop
+    fn run(&mut self, chip8: &mut Chip8, command: &str) -> Result<String, String> {
+        let words: Vec<&str> = command.split_whitespace().collect();
+        let relation = match words.as_slice() {
+            [] => return Ok(String::new()),
+            ["new"] => {
+                let search = RamSearch::new(chip8);
+                let message = format!("{} candidates", search.len());
+                self.search = Some(search);
+                return Ok(message);
+            }
+            ["="] => Relation::Equal,
+            ["!="] => Relation::Changed,
+            ["+"] => Relation::Increased,
+            ["-"] => Relation::Decreased,
+            ["=", value] => Relation::EqualTo(parse_value(value)?),
+            ["watch", address] => {
+                let address = parse_address(address)?;
+                self.watchpoints.retain(|watch| watch.address != address);
+                self.watchpoints.push(Watchpoint::new(chip8, address));
+                return Ok(format!("watching {:03X}", address));
+            }
+            ["unwatch", address] => {
+                let address = parse_address(address)?;
+                self.watchpoints.retain(|watch| watch.address != address);
+                return Ok(format!("stopped watching {:03X}", address));
+            }
+            ["cheat", address, value @ ..] if value.len() < 2 => {
+                let address = parse_address(address)?;
+                let value = match value {
+                    [value] => parse_value(value)?,
+                    _ => chip8.memory()[address as usize],
+                };
+                self.cheats.retain(|cheat| !freezes(cheat, address));
+                self.cheats.push(Cheat {
+                    name: format!("ram {:03x}", address),
+                    mode: Mode::Freeze,
+                    codes: vec![Code {
+                        target: Target::Memory(address),
+                        value,
+                    }],
+                    enabled: true,
+                });
+                return Ok(format!("{:03X} frozen at {:02X}", address, value));
+            }
+            ["uncheat", address] => {
+                let address = parse_address(address)?;
+                self.cheats.retain(|cheat| !freezes(cheat, address));
+                return Ok(format!("{:03X} unfrozen", address));
+            }
+            _ => return Err(format!("unknown command {}", command)),
+        };
+        let search = self
+            .search
+            .as_mut()
+            .ok_or_else(|| "start a search with new".to_string())?;
+        Ok(format!("{} candidates", search.filter(chip8, relation)))
+    }
+}
+
+fn freezes(cheat: &Cheat, address: u16) -> bool {
+    cheat
+        .codes
+        .iter()
+        .any(|code| code.target == Target::Memory(address))
+}
+
+fn parse_value(text: &str) -> Result<u8, String> {
+    u8::from_str_radix(text, 16).map_err(|_| format!("bad value {}", text))
+}
+
+fn parse_address(text: &str) -> Result<u16, String> {
+    u16::from_str_radix(text, 16)
+        .ok()
+        .filter(|address| *address < 0x1000)
+        .ok_or_else(|| format!("bad address {}", text))
+}
+
+fn panel(chip8: &Chip8, status: &str, debugger: &Debugger) -> Vec<String> {
+    let mut lines = vec![
+        format!("PC {:03X}  I {:03X}", chip8.pc(), chip8.index()),
+        format!(
+            "DT {:02X}   ST {:02X}",
+            chip8.delay_timer(),
+            chip8.sound_timer()
+        ),
+    ];
+    for (n, regs) in chip8.v().chunks(4).enumerate() {
+        let regs: Vec<String> = regs
+            .iter()
+            .enumerate()
+            .map(|(i, v)| format!("V{:X} {:02X}", n * 4 + i, v))
+            .collect();
+        lines.push(regs.join("  "));
+    }
+    let stack: Vec<String> = chip8
+        .stack()
+        .iter()
+        .map(|address| format!("{:03X}", address))
+        .collect();
+    lines.push(format!("stack {}", stack.join(" ")));
+
+    if let Some(search) = &debugger.search {
+        lines.push(String::new());
+        lines.push(format!(
+            "search {} candidates, pass {}",
+            search.len(),
+            search.passes()
+        ));
+        for candidate in search.candidates(chip8).iter().take(RESULTS_SHOWN) {
+            lines.push(format!(
+                "  {:03X} {:02X}  was {:02X}",
+                candidate.address, candidate.value, candidate.previous
+            ));
+        }
+    }
+    if !debugger.watchpoints.is_empty() || !debugger.cheats.is_empty() {
+        lines.push(String::new());
+    }
+    for watch in &debugger.watchpoints {
+        lines.push(format!("watch {:03X} {:02X}", watch.address, watch.value()));
+    }
+    for cheat in &debugger.cheats {
+        for code in &cheat.codes {
+            if let Target::Memory(address) = code.target {
+                lines.push(format!("cheat {:03X} {:02X}", address, code.value));
+            }
+        }
+    }
+
+    lines.push(String::new());
+    lines.push(status.to_string());
+    match &debugger.prompt {
+        Some(prompt) => lines.push(format!(":{}_", prompt)),
+        None => {
+            lines.push("esc quit  p pause  n step frame  backspace reset".to_string());
+            lines.push(": new = != + - =N watch ADDR cheat ADDR".to_string());
+        }
+    }
+    lines
+}
+
+struct Terminal {
+    out: io::Stdout,
+    // whether the terminal reports key releases
+    releases: bool,
+}
+
+impl Terminal {
+    fn open() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        let mut out = io::stdout();
+        execute!(
+            out,
+            terminal::EnterAlternateScreen,
+            cursor::Hide,
+            terminal::Clear(terminal::ClearType::All)
+        )?;
+        let releases = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        if releases {
+            execute!(
+                out,
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+            )?;
+        }
+        Ok(Self { out, releases })
+    }
+
+    fn draw(&mut self, screen: &[String], panel: &[String]) -> io::Result<()> {
+        let border = "─".repeat(screen.first().map_or(0, |line| line.chars().count()));
+        let mut lines = vec![format!("┌{}┐", border)];
+        lines.extend(screen.iter().map(|line| format!("│{}│", line)));
+        lines.push(format!("└{}┘", border));
+
+        for row in 0..lines.len().max(panel.len()) {
+            let left = lines.get(row).map_or("", |line| line.as_str());
+            let right = panel.get(row).map_or("", |line| line.as_str());
+            queue!(
+                self.out,
+                cursor::MoveTo(0, row as u16),
+                style::Print(left),
+                style::Print("  "),
+                style::Print(right),
+                terminal::Clear(terminal::ClearType::UntilNewLine)
+            )?;
+        }
+        self.out.flush()
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        if self.releases {
+            let _ = execute!(self.out, PopKeyboardEnhancementFlags);
+        }
+        let _ = execute!(self.out, cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+// run the interpreter in the terminal until escape or ctrl-c. the ROM has to be loaded already.
+// cheats made with the RAM search are printed on the way out, ready for a cheat file
+pub fn run(chip8: &mut Chip8) -> io::Result<()> {
+    let mut debugger = Debugger::default();
+    let result = run_terminal(chip8, &mut debugger);
+    if !debugger.cheats.is_empty() {
+        println!("[{:08x}]", romdb::rom_hash(chip8.rom()));
+        for cheat in &debugger.cheats {
+            println!("{}", cheat);
+        }
+    }
+    result
+}
+
+fn run_terminal(chip8: &mut Chip8, debugger: &mut Debugger) -> io::Result<()> {
+    let mut term = Terminal::open()?;
+    // when each key was last seen going down, None while up
+    let mut held: [Option<Instant>; 16] = [None; 16];
+    let mut paused = false;
+    let mut message = String::new();
+    let mut frame = 0u32;
+    let mut next_frame = Instant::now();
+
+    loop {
+        let mut advance = false;
+        while event::poll(Duration::ZERO)? {
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            let down = key.kind != KeyEventKind::Release;
+            if debugger.prompt.is_some() {
+                if down {
+                    if let Some(outcome) = debugger.type_key(chip8, key.code) {
+                        message = outcome;
+                    }
+                }
+                continue;
+            }
+            match key.code {
+                KeyCode::Esc => return Ok(()),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    return Ok(())
+                }
+                KeyCode::Char('p') if down => {
+                    paused = !paused;
+                    message.clear();
+                }
+                KeyCode::Char('n') if down => advance = paused,
+                KeyCode::Char(':') if down => {
+                    debugger.prompt = Some(String::new());
+                    held = [None; 16];
+                }
+                KeyCode::Backspace if down => {
+                    chip8.reset();
+                    message = "Reset".to_string();
+                }
+                KeyCode::Char(c) => {
+                    let c = c.to_ascii_lowercase();
+                    if let Some((_, chip8_key)) = KEYS.iter().find(|(k, _)| *k == c) {
+                        held[*chip8_key] = down.then(Instant::now);
+                    }
+                }
+                _ => {}
+            }
+        }
+        for (key, since) in held.iter_mut().enumerate() {
+            if !term.releases && since.is_some_and(|since| since.elapsed() > KEY_HOLD) {
+                *since = None;
+            }
+            chip8.set_key(key, since.is_some());
+        }
+
+        let was_beeping = chip8.sound_active();
+        if (!paused || advance) && debugger.prompt.is_none() && !chip8.is_halted() {
+            for cheat in &debugger.cheats {
+                cheat.write(chip8);
+            }
+            if let Err(error) = chip8.run_frame() {
+                message = error.to_string();
+                paused = !chip8.is_halted();
+            }
+            for watch in &mut debugger.watchpoints {
+                if let Some((previous, value)) = watch.check(chip8) {
+                    message = format!(
+                        "{:03X} changed {:02X} -> {:02X}",
+                        watch.address, previous, value
+                    );
+                    paused = true;
+                }
+            }
+        }
+        // the terminal bell stands in for the buzzer
+        if chip8.sound_active() && !was_beeping {
+            queue!(term.out, style::Print('\x07'))?;
+        }
+
+        if chip8.is_drawflag_set() || frame.is_multiple_of(PANEL_INTERVAL) {
+            chip8.clear_drawflag();
+            let status = if chip8.is_halted() {
+                format!("halted: {}", message)
+            } else if paused {
+                format!("paused {}", message)
+            } else {
+                message.clone()
+            };
+            let screen = half_blocks(chip8.framebuffer(), SCREEN_WIDTH, SCREEN_HEIGHT);
+            term.draw(&screen, &panel(chip8, &status, debugger))?;
+        }
+        frame = frame.wrapping_add(1);
+
+        next_frame += FRAME_DURATION;
+        let now = Instant::now();
+        if next_frame > now {
+            std::thread::sleep(next_frame - now);
+        } else {
+            next_frame = now;
+        }
+    }
+}
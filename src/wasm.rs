@@ -0,0 +1,79 @@
+use wasm_bindgen::prelude::*;
+
+use crate::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+// the interpreter for javascript. errors come back as exceptions carrying the message
+#[wasm_bindgen(js_name = Chip8)]
+pub struct WasmChip8 {
+    chip8: crate::Chip8,
+}
+
+#[wasm_bindgen(js_class = Chip8)]
+impl WasmChip8 {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self {
+            chip8: crate::Chip8::new(),
+        }
+    }
+
+    pub fn width() -> usize {
+        SCREEN_WIDTH
+    }
+
+    pub fn height() -> usize {
+        SCREEN_HEIGHT
+    }
+
+    #[wasm_bindgen(js_name = loadRom)]
+    pub fn load_rom(&mut self, rom: &[u8]) -> Result<(), JsError> {
+        self.chip8
+            .load_rom(rom)
+            .map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    pub fn reset(&mut self) {
+        self.chip8.reset();
+    }
+
+    // one 60Hz frame, call it from requestAnimationFrame
+    #[wasm_bindgen(js_name = runFrame)]
+    pub fn run_frame(&mut self) -> Result<(), JsError> {
+        self.chip8
+            .run_frame()
+            .map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    // one byte per pixel, 1 for lit, row by row
+    pub fn framebuffer(&self) -> Vec<u8> {
+        self.chip8
+            .framebuffer()
+            .iter()
+            .map(|pixel| *pixel as u8)
+            .collect()
+    }
+
+    // true when the screen changed since the last call
+    #[wasm_bindgen(js_name = takeDrawFlag)]
+    pub fn take_drawflag(&mut self) -> bool {
+        let draw = self.chip8.is_drawflag_set();
+        self.chip8.clear_drawflag();
+        draw
+    }
+
+    #[wasm_bindgen(js_name = setKey)]
+    pub fn set_key(&mut self, key: usize, pressed: bool) {
+        self.chip8.set_key(key, pressed);
+    }
+
+    #[wasm_bindgen(js_name = soundActive)]
+    pub fn sound_active(&self) -> bool {
+        self.chip8.sound_active()
+    }
+}
+
+impl Default for WasmChip8 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,47 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+
+// how often the watched files are checked
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+// notices when files change on disk by polling their modification time
+pub struct FileWatcher {
+    files: Vec<(PathBuf, Option<SystemTime>)>,
+    last_check: Instant,
+}
+
+impl FileWatcher {
+    pub fn new(paths: &[&str]) -> Self {
+        Self {
+            files: paths
+                .iter()
+                .map(|path| (PathBuf::from(path), Self::modified(path)))
+                .collect(),
+            last_check: Instant::now(),
+        }
+    }
+
+    fn modified(path: impl AsRef<std::path::Path>) -> Option<SystemTime> {
+        fs::metadata(path).and_then(|meta| meta.modified()).ok()
+    }
+
+    // true if any of the files changed since the last call. cheap to call every frame
+    pub fn changed(&mut self) -> bool {
+        if self.last_check.elapsed() < POLL_INTERVAL {
+            return false;
+        }
+        self.last_check = Instant::now();
+
+        let mut changed = false;
+        for (path, modified) in self.files.iter_mut() {
+            let now = Self::modified(&path);
+            // a file that is being rewritten can briefly disappear, wait for it to come back
+            if now.is_some() && now != *modified {
+                *modified = now;
+                changed = true;
+            }
+        }
+        changed
+    }
+}
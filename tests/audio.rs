@@ -0,0 +1,82 @@
+// renders the buzzer with the headless audio backend while a program runs the sound timer. needs
+// the SDL library but no audio device
+#![cfg(feature = "sdl")]
+
+use chip8::{Audio, Chip8, Tone, Waveform, SAMPLES_PER_FRAME, SAMPLE_RATE};
+
+// LD V5, 5; LD ST, V5; then loop forever
+const BEEP: [u8; 6] = [0x65, 0x05, 0xf5, 0x18, 0x12, 0x04];
+
+const FRAMES: usize = 10;
+
+// run the program a frame at a time, feeding the buzzer to the audio like the emulator does
+fn render(audio: &mut Audio, mut frame: impl FnMut(&[f32])) {
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&BEEP).unwrap();
+    for _ in 0..FRAMES {
+        chip8.run_frame().unwrap();
+        audio.set_active(chip8.sound_active());
+        frame(audio.last_frame());
+    }
+}
+
+#[test]
+fn activity_follows_the_sound_timer() {
+    let mut audio = Audio::headless(Tone::default(), None).unwrap();
+    render(&mut audio, |_| {});
+    // the timer is set to 5 in the first frame and counts down once per frame
+    let mut expected = vec![true; 5];
+    expected.resize(FRAMES, false);
+    assert_eq!(audio.activity(), &expected[..]);
+}
+
+// a square wave at a quarter of the sample rate, so every phase is exact in f32: high for three
+// samples, low for one, with no envelope
+fn quarter_rate_square() -> Tone {
+    Tone {
+        frequency: SAMPLE_RATE as f32 / 4.0,
+        volume: 0.5,
+        waveform: Waveform::Square,
+        attack: 0.0,
+        release: 0.0,
+    }
+}
+
+#[test]
+fn wav_file_holds_the_expected_samples() {
+    let path = format!("{}/audio_test.wav", env!("CARGO_TARGET_TMPDIR"));
+    let mut audio = Audio::headless(quarter_rate_square(), Some(&path)).unwrap();
+    render(&mut audio, |_| {});
+    drop(audio);
+    let wav = std::fs::read(&path).unwrap();
+
+    let u16_at = |at: usize| u16::from_le_bytes([wav[at], wav[at + 1]]);
+    let u32_at = |at: usize| u32::from_le_bytes(wav[at..at + 4].try_into().unwrap());
+    assert_eq!(&wav[0..4], b"RIFF");
+    assert_eq!(u32_at(4) as usize, wav.len() - 8);
+    assert_eq!(&wav[8..16], b"WAVEfmt ");
+    // 16-bit mono PCM
+    assert_eq!(u16_at(20), 1);
+    assert_eq!(u16_at(22), 1);
+    assert_eq!(u32_at(24), SAMPLE_RATE as u32);
+    assert_eq!(u32_at(28), SAMPLE_RATE as u32 * 2);
+    assert_eq!(u16_at(32), 2);
+    assert_eq!(u16_at(34), 16);
+    assert_eq!(&wav[36..40], b"data");
+    assert_eq!(u32_at(40) as usize, FRAMES * SAMPLES_PER_FRAME * 2);
+
+    let samples: Vec<i16> = wav[44..]
+        .chunks(2)
+        .map(|sample| i16::from_le_bytes([sample[0], sample[1]]))
+        .collect();
+    // the phase keeps running while the timer is off, the first 5 frames are heard
+    let high = (0.5 * 32767.0f32).round() as i16;
+    let expected: Vec<i16> = (0..FRAMES * SAMPLES_PER_FRAME)
+        .map(|n| match (n / SAMPLES_PER_FRAME < 5, n % 4) {
+            (false, _) => 0,
+            (true, 3) => -high,
+            (true, _) => high,
+        })
+        .collect();
+    assert_eq!(samples, expected);
+}
@@ -0,0 +1,56 @@
+// builds the static library, compiles examples/c/headless.c against it and the generated header
+// and runs it. needs a C compiler, run with
+// cargo test --no-default-features --features capi --test c_api
+#![cfg(feature = "capi")]
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// cargo test only builds the rlib, so build the static library the way the readme does. it goes
+// to its own target directory, the one this test runs from is locked by the cargo running it
+fn build_static_library(root: &Path, target_dir: &Path) -> PathBuf {
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let status = Command::new(cargo)
+        .args([
+            "rustc",
+            "--lib",
+            "--no-default-features",
+            "--features",
+            "capi",
+        ])
+        .args(["--crate-type", "staticlib"])
+        .current_dir(root)
+        .env("CARGO_TARGET_DIR", target_dir)
+        .status()
+        .expect("can't run cargo");
+    assert!(status.success(), "the static library doesn't build");
+    target_dir.join("debug/libchip8.a")
+}
+
+#[test]
+fn c_example_runs() {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let out_dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR"));
+    let library = build_static_library(&root, &out_dir.join("capi"));
+    let binary = out_dir.join("headless");
+    let cc = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let status = Command::new(cc)
+        .args(["-std=c99", "-Wall", "-Wextra", "-Werror", "-I"])
+        .arg(root.join("include"))
+        .arg(root.join("examples/c/headless.c"))
+        .arg(&library)
+        .args(["-lpthread", "-ldl", "-lm", "-o"])
+        .arg(&binary)
+        .status()
+        .expect("can't run the C compiler");
+    assert!(status.success(), "the C example doesn't compile");
+
+    let output = Command::new(&binary)
+        .arg(root.join("games/PONG"))
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains("restored"), "{}", stdout);
+    assert!(stdout.contains('#'), "nothing on screen:\n{}", stdout);
+}
@@ -0,0 +1,68 @@
+// plays the bundled games through the Gym style environment with their specs
+use chip8::gym::{Environment, Spec};
+
+fn environment(game: &str) -> Environment {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let rom = std::fs::read(format!("{}/games/{}", root, game)).unwrap();
+    let spec = Spec::load(&format!("{}/specs/{}.spec", root, game)).unwrap();
+    Environment::new(&rom, spec).unwrap()
+}
+
+#[test]
+fn brix_rewards_broken_bricks() {
+    let mut env = environment("BRIX");
+    env.reset();
+    // without moving the paddle the ball still breaks bricks before all lives are gone
+    let (mut points, mut steps) = (0.0, 0);
+    loop {
+        let (_, reward, done) = env.step(0).unwrap();
+        if reward > 0.0 {
+            points += reward;
+        }
+        steps += 1;
+        if done {
+            break;
+        }
+        assert!(steps < 10_000, "the episode never ended");
+    }
+    let score = env.chip8().v()[5];
+    assert!(score > 0, "no bricks broken");
+    // the score in V5 and its BCD digits agree
+    let memory = env.chip8().memory();
+    let digits = memory[0x314] as u32 * 100 + memory[0x315] as u32 * 10 + memory[0x316] as u32;
+    assert_eq!(digits, score as u32);
+    assert!(
+        points >= score as f64,
+        "{} points for a score of {}",
+        points,
+        score
+    );
+    assert_eq!(env.chip8().v()[0xe], 0);
+}
+
+#[test]
+fn unknown_actions_are_an_error() {
+    let mut env = environment("PONG");
+    assert!(env.step(env.action_space()).is_err());
+    assert_eq!(env.action_keys(3), None);
+    assert_eq!(env.action_keys(1), Some(&[0x1][..]));
+}
+
+#[test]
+fn seeded_episodes_repeat() {
+    let play = |seed: u64| {
+        let mut env = environment("PONG");
+        env.set_sticky_actions(0.25);
+        env.seed(seed);
+        let mut observations = vec![env.reset()];
+        for step in 0..300 {
+            let (observation, _, done) = env.step(step % env.action_space()).unwrap();
+            observations.push(observation);
+            if done {
+                break;
+            }
+        }
+        observations
+    };
+    assert_eq!(play(7), play(7));
+}
@@ -0,0 +1,250 @@
+// runs small programs on the interpreter and checks the machine state afterwards
+use chip8::{Chip8, Chip8Error, FaultPolicy, Quirks, Step};
+
+fn load(program: &[u8]) -> Chip8 {
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(program).unwrap();
+    chip8
+}
+
+// LD V3, K
+const WAIT_FOR_KEY: [u8; 2] = [0xf3, 0x0a];
+
+#[test]
+fn fx0a_waits_for_the_release() {
+    let mut chip8 = load(&WAIT_FOR_KEY);
+    assert_eq!(chip8.step().unwrap(), Step::WaitingForKey);
+
+    chip8.set_key(0x7, true);
+    assert_eq!(chip8.step().unwrap(), Step::WaitingForKey);
+    assert_eq!(chip8.step().unwrap(), Step::WaitingForKey);
+    assert_eq!(chip8.v()[3], 0);
+    assert_eq!(chip8.pc(), 0x200);
+
+    chip8.set_key(0x7, false);
+    assert_eq!(
+        chip8.step().unwrap(),
+        Step::Executed {
+            address: 0x200,
+            opcode: 0xf30a
+        }
+    );
+    assert_eq!(chip8.v()[3], 0x7);
+    assert_eq!(chip8.pc(), 0x202);
+}
+
+#[test]
+fn fx0a_ignores_keys_held_before_the_wait() {
+    let mut chip8 = load(&WAIT_FOR_KEY);
+    chip8.set_key(0x1, true);
+    chip8.tick_timers();
+    assert_eq!(chip8.step().unwrap(), Step::WaitingForKey);
+    chip8.set_key(0x1, false);
+    assert_eq!(chip8.step().unwrap(), Step::WaitingForKey);
+
+    chip8.set_key(0x2, true);
+    assert_eq!(chip8.step().unwrap(), Step::WaitingForKey);
+    chip8.set_key(0x2, false);
+    chip8.step().unwrap();
+    assert_eq!(chip8.v()[3], 0x2);
+}
+
+fn run_with(quirks: &str, program: &[u8], steps: usize) -> Chip8 {
+    let mut chip8 = load(program);
+    chip8.set_quirks(Quirks::from_name(quirks).unwrap());
+    for _ in 0..steps {
+        chip8.step().unwrap();
+    }
+    chip8
+}
+
+#[test]
+fn shift_quirk_picks_the_source_register() {
+    // LD V1, 0x81; LD V2, 0x03; SHR V1, V2; SHL V3, V1
+    let program = [0x61, 0x81, 0x62, 0x03, 0x81, 0x26, 0x83, 0x1e];
+    let chip8 = run_with("default", &program, 3);
+    assert_eq!((chip8.v()[1], chip8.v()[0xf]), (0x40, 1));
+    let chip8 = run_with("chip8", &program, 3);
+    assert_eq!((chip8.v()[1], chip8.v()[0xf]), (0x01, 1));
+    let chip8 = run_with("chip8", &program, 4);
+    assert_eq!((chip8.v()[3], chip8.v()[0xf]), (0x02, 0));
+}
+
+#[test]
+fn load_quirk_moves_i() {
+    // LD I, 0x300; LD [I], V2; LD V2, [I]
+    let program = [0xa3, 0x00, 0xf2, 0x55, 0xf2, 0x65];
+    assert_eq!(run_with("schip", &program, 2).index(), 0x300);
+    assert_eq!(run_with("chip8", &program, 2).index(), 0x303);
+    assert_eq!(run_with("xochip", &program, 3).index(), 0x306);
+}
+
+#[test]
+fn jump_quirk_adds_vx() {
+    // LD V0, 2; LD V3, 4; JP V0, 0x310
+    let program = [0x60, 0x02, 0x63, 0x04, 0xb3, 0x10];
+    assert_eq!(run_with("default", &program, 3).pc(), 0x312);
+    assert_eq!(run_with("schip", &program, 3).pc(), 0x314);
+}
+
+#[test]
+fn vf_reset_quirk_clears_vf() {
+    // LD VF, 1; LD V1, 0x0c; OR V1, V1; LD VF, 1; AND V1, V1; LD VF, 1; XOR V1, V1
+    let program = [
+        0x6f, 0x01, 0x61, 0x0c, 0x81, 0x11, 0x6f, 0x01, 0x81, 0x12, 0x6f, 0x01, 0x81, 0x13,
+    ];
+    for steps in [3, 5, 7] {
+        assert_eq!(
+            run_with("chip8", &program, steps).v()[0xf],
+            0,
+            "{} steps",
+            steps
+        );
+        assert_eq!(
+            run_with("xochip", &program, steps).v()[0xf],
+            1,
+            "{} steps",
+            steps
+        );
+    }
+    assert_ne!(Quirks::from_name("chip8"), Quirks::from_name("xochip"));
+}
+
+#[test]
+fn reload_starts_the_reports_over() {
+    // JP 0x202; JP 0x202
+    let mut chip8 = load(&[0x12, 0x02, 0x12, 0x02]);
+    chip8.enable_profiler();
+    chip8.enable_coverage();
+    chip8.run_frame().unwrap();
+    assert!(chip8.profiler().unwrap().count_at(0x202) > 0);
+
+    // JP 0x200
+    chip8.reload_rom(&[0x12, 0x00], false, false).unwrap();
+    chip8.run_frame().unwrap();
+    let profiler = chip8.profiler().unwrap();
+    assert_eq!(profiler.count_at(0x202), 0);
+    assert_eq!(profiler.total(), 10);
+    assert_eq!(chip8.coverage().unwrap().flags(0x202), 0);
+    assert_ne!(chip8.coverage().unwrap().flags(0x200), 0);
+}
+
+#[test]
+fn faults_follow_the_opcode_class() {
+    use chip8::opcodes::{class, mnemonic, Class};
+    assert_eq!(class(0x0123), Class::Sys);
+    assert_eq!(class(0x5121), Class::Unknown);
+    assert_eq!(class(0xd125).to_string(), "DXYN");
+    assert_eq!(mnemonic(0x5121), "dw   5121");
+
+    // 200: SYS 123
+    let mut chip8 = load(&[0x01, 0x23]);
+    chip8.set_fault_policy(FaultPolicy::Ignore);
+    assert!(matches!(
+        chip8.step(),
+        Ok(Step::Skipped(Chip8Error::MachineCode {
+            address: 0x200,
+            opcode: 0x0123
+        }))
+    ));
+}
+
+// the accesses through I wrap around the end of memory instead of running off it
+fn run_at_last_byte(program: &[u8], steps: usize) -> Chip8 {
+    let mut chip8 = load(program);
+    chip8.memory_mut()[0xfff] = 0x81;
+    chip8.memory_mut()[0x000] = 0x42;
+    chip8.memory_mut()[0x001] = 0x24;
+    for _ in 0..steps {
+        chip8.step().unwrap();
+    }
+    chip8
+}
+
+#[test]
+fn dxyn_wraps_past_the_end_of_memory() {
+    // 200: LD I, FFF; 202: DRW V0, V0, 2
+    let chip8 = run_at_last_byte(&[0xaf, 0xff, 0xd0, 0x02], 2);
+    let screen = chip8.framebuffer();
+    assert!(screen[0] && screen[7]);
+    assert!(screen[64 + 1] && screen[64 + 6]);
+    assert_eq!(screen.iter().filter(|lit| **lit).count(), 4);
+}
+
+#[test]
+fn fx33_wraps_past_the_end_of_memory() {
+    // 200: LD V0, 7B; 202: LD I, FFF; 204: LD B, V0
+    let chip8 = run_at_last_byte(&[0x60, 0x7b, 0xaf, 0xff, 0xf0, 0x33], 3);
+    assert_eq!(chip8.memory()[0xfff], 1);
+    assert_eq!(chip8.memory()[0x000..0x002], [2, 3]);
+}
+
+#[test]
+fn fx55_wraps_past_the_end_of_memory() {
+    // 200: LD V0, 11; 202: LD V1, 22; 204: LD V2, 33; 206: LD I, FFF; 208: LD [I], V2
+    let program = [0x60, 0x11, 0x61, 0x22, 0x62, 0x33, 0xaf, 0xff, 0xf2, 0x55];
+    let chip8 = run_at_last_byte(&program, 5);
+    assert_eq!(chip8.memory()[0xfff], 0x11);
+    assert_eq!(chip8.memory()[0x000..0x002], [0x22, 0x33]);
+}
+
+#[test]
+fn fx65_wraps_past_the_end_of_memory() {
+    // 200: LD I, FFF; 202: LD V2, [I]
+    let chip8 = run_at_last_byte(&[0xaf, 0xff, 0xf2, 0x65], 2);
+    assert_eq!(chip8.v()[0..3], [0x81, 0x42, 0x24]);
+}
+
+#[test]
+fn index_past_memory_wraps_around() {
+    // 200: LD I, FFF; 202: LD V0, 02; 204: ADD I, V0; 206: LD V2, [I]
+    let program = [0xaf, 0xff, 0x60, 0x02, 0xf0, 0x1e, 0xf2, 0x65];
+    let chip8 = run_at_last_byte(&program, 4);
+    assert_eq!(chip8.index(), 0x1001);
+    assert_eq!(chip8.v()[0..3], chip8.memory()[0x001..0x004]);
+    assert_eq!(chip8.v()[0], 0x24);
+}
+
+#[test]
+fn subtraction_sets_vf_when_there_is_no_borrow() {
+    // 200: LD V0, 05; 202: LD V1, 03; 204: SUB V0, V1; 206: LD V2, VF
+    // 208: SUB V0, V1 borrows; 20A: LD V3, VF
+    let program = [
+        0x60, 0x05, 0x61, 0x03, 0x80, 0x15, 0x82, 0xf0, 0x80, 0x15, 0x83, 0xf0,
+    ];
+    let mut chip8 = load(&program);
+    for _ in 0..6 {
+        chip8.step().unwrap();
+    }
+    assert_eq!(chip8.v()[0..4], [0xff, 0x03, 0x01, 0x00]);
+}
+
+#[test]
+fn reverse_subtraction_sets_vf_when_there_is_no_borrow() {
+    // 200: LD V0, 03; 202: LD V1, 05; 204: SUBN V0, V1; 206: LD V2, VF
+    // 208: SUBN V1, V0 borrows; 20A: LD V3, VF
+    let program = [
+        0x60, 0x03, 0x61, 0x05, 0x80, 0x17, 0x82, 0xf0, 0x81, 0x07, 0x83, 0xf0,
+    ];
+    let mut chip8 = load(&program);
+    for _ in 0..6 {
+        chip8.step().unwrap();
+    }
+    assert_eq!(chip8.v()[0..4], [0x02, 0xfd, 0x01, 0x00]);
+}
+
+#[test]
+fn seeded_random_numbers_repeat() {
+    // 200: RND V0, FF; 202: RND V1, FF; 204: RND V2, FF; 206: RND V3, FF
+    let program = [0xc0, 0xff, 0xc1, 0xff, 0xc2, 0xff, 0xc3, 0xff];
+    let run = |seed: u64| {
+        let mut chip8 = load(&program);
+        chip8.seed_rng(seed);
+        for _ in 0..4 {
+            chip8.step().unwrap();
+        }
+        chip8.v()[0..4].to_vec()
+    };
+    assert_eq!(run(3), run(3));
+    assert_ne!(run(3), run(4));
+}
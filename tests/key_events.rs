@@ -0,0 +1,68 @@
+// feeds keypad states to the edge queue the hosts fill from their input
+use chip8::{KeyEvent, KeyEvents, KEY_EVENT_QUEUE_LEN};
+
+fn keys(pressed: &[usize]) -> [bool; 16] {
+    let mut keys = [false; 16];
+    for key in pressed {
+        keys[*key] = true;
+    }
+    keys
+}
+
+#[test]
+fn press_then_release_is_two_events() {
+    let mut events = KeyEvents::default();
+    events.update(&keys(&[0x5]));
+    events.update(&keys(&[0x5]));
+    events.update(&keys(&[]));
+    assert_eq!(events.len(), 2);
+    assert_eq!(events.pop(), Some(KeyEvent::Pressed(0x5)));
+    assert_eq!(events.pop(), Some(KeyEvent::Released(0x5)));
+    assert_eq!(events.pop(), None);
+}
+
+#[test]
+fn keys_changing_together_come_in_key_order() {
+    let mut events = KeyEvents::default();
+    events.update(&keys(&[0x1, 0xa]));
+    events.update(&keys(&[0x2, 0xa]));
+    let all: Vec<KeyEvent> = std::iter::from_fn(|| events.pop()).collect();
+    assert_eq!(
+        all,
+        vec![
+            KeyEvent::Pressed(0x1),
+            KeyEvent::Pressed(0xa),
+            KeyEvent::Released(0x1),
+            KeyEvent::Pressed(0x2),
+        ]
+    );
+}
+
+#[test]
+fn a_full_queue_drops_the_oldest_events() {
+    let mut events = KeyEvents::default();
+    // 32 presses and releases of key 3 fill the queue, the press of key 4 pushes out the first
+    for _ in 0..32 {
+        events.update(&keys(&[0x3]));
+        events.update(&keys(&[]));
+    }
+    events.update(&keys(&[0x4]));
+    assert_eq!(events.len(), KEY_EVENT_QUEUE_LEN);
+
+    let all: Vec<KeyEvent> = std::iter::from_fn(|| events.pop()).collect();
+    assert_eq!(all.len(), 64);
+    assert_eq!(all[0], KeyEvent::Released(0x3));
+    assert_eq!(all[1], KeyEvent::Pressed(0x3));
+    assert_eq!(all[62], KeyEvent::Released(0x3));
+    assert_eq!(all[63], KeyEvent::Pressed(0x4));
+}
+
+#[test]
+fn clear_forgets_held_keys() {
+    let mut events = KeyEvents::default();
+    events.update(&keys(&[0x7]));
+    events.clear();
+    assert!(events.is_empty());
+    events.update(&keys(&[0x7]));
+    assert_eq!(events.pop(), Some(KeyEvent::Pressed(0x7)));
+}
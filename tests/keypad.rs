@@ -0,0 +1,163 @@
+// drives the keypad with synthetic controller events through Keypad::inject_event. needs the SDL
+// library but no window or real controller
+#![cfg(feature = "sdl")]
+
+use std::sync::{Mutex, MutexGuard};
+
+use chip8::{Chip8, KeyEvent, Keypad};
+use sdl2::controller::{Axis, Button};
+use sdl2::event::Event;
+
+// SDL can only be initialized once at a time, the tests take turns
+static SDL: Mutex<()> = Mutex::new(());
+
+fn lock_sdl() -> MutexGuard<'static, ()> {
+    SDL.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+// instance id of the virtual controller, far above the ones SDL gives real controllers
+const PAD: u32 = 1000;
+
+fn keypad() -> Keypad {
+    let sdl_context = sdl2::init().unwrap();
+    let mut keypad = Keypad::new(&sdl_context);
+    assert!(keypad.connect_virtual_controller(PAD));
+    keypad
+}
+
+fn pressed(keypad: &mut Keypad) -> Vec<usize> {
+    (0..16).filter(|key| keypad.is_key_pressed(*key)).collect()
+}
+
+#[test]
+fn buttons_press_their_keys() {
+    let _sdl = lock_sdl();
+    let mut keypad = keypad();
+    assert_eq!(pressed(&mut keypad), Vec::<usize>::new());
+
+    keypad.inject_event(Event::ControllerButtonDown {
+        timestamp: 0,
+        which: PAD,
+        button: Button::A,
+    });
+    keypad.inject_event(Event::ControllerButtonDown {
+        timestamp: 0,
+        which: PAD,
+        button: Button::DPadUp,
+    });
+    assert_eq!(pressed(&mut keypad), vec![0x2, 0x5]);
+
+    keypad.inject_event(Event::ControllerButtonUp {
+        timestamp: 0,
+        which: PAD,
+        button: Button::A,
+    });
+    assert_eq!(pressed(&mut keypad), vec![0x2]);
+}
+
+#[test]
+fn axes_press_keys_outside_the_deadzone() {
+    let _sdl = lock_sdl();
+    let mut keypad = keypad();
+    let deadzone = keypad.keymap().deadzone();
+    let axis = |keypad: &mut Keypad, value: i16| {
+        keypad.inject_event(Event::ControllerAxisMotion {
+            timestamp: 0,
+            which: PAD,
+            axis: Axis::LeftX,
+            value,
+        });
+        pressed(keypad)
+    };
+
+    assert_eq!(axis(&mut keypad, deadzone), Vec::<usize>::new());
+    assert_eq!(axis(&mut keypad, deadzone + 1), vec![0x6]);
+    assert_eq!(axis(&mut keypad, -deadzone - 1), vec![0x4]);
+    assert_eq!(axis(&mut keypad, 0), Vec::<usize>::new());
+}
+
+#[test]
+fn unknown_controllers_are_ignored() {
+    let _sdl = lock_sdl();
+    let mut keypad = keypad();
+    keypad.inject_event(Event::ControllerButtonDown {
+        timestamp: 0,
+        which: PAD + 1,
+        button: Button::A,
+    });
+    assert_eq!(pressed(&mut keypad), Vec::<usize>::new());
+
+    keypad.inject_event(Event::ControllerDeviceRemoved {
+        timestamp: 0,
+        which: PAD,
+    });
+    keypad.inject_event(Event::ControllerButtonDown {
+        timestamp: 0,
+        which: PAD,
+        button: Button::A,
+    });
+    assert_eq!(pressed(&mut keypad), Vec::<usize>::new());
+}
+
+#[test]
+fn devices_sdl_cant_open_are_not_connected() {
+    let _sdl = lock_sdl();
+    let sdl_context = sdl2::init().unwrap();
+    let mut keypad = Keypad::new(&sdl_context);
+    keypad.inject_event(Event::ControllerDeviceAdded {
+        timestamp: 0,
+        which: PAD,
+    });
+    keypad.inject_event(Event::ControllerButtonDown {
+        timestamp: 0,
+        which: PAD,
+        button: Button::A,
+    });
+    assert_eq!(pressed(&mut keypad), Vec::<usize>::new());
+}
+
+#[test]
+fn pad_keys_reach_the_interpreter() {
+    let _sdl = lock_sdl();
+    let mut keypad = keypad();
+    keypad.inject_event(Event::ControllerButtonDown {
+        timestamp: 0,
+        which: PAD,
+        button: Button::B,
+    });
+
+    // SKP V0 with V0 = 0, then LD V1, 1 only runs if the key is up
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&[0xe0, 0x9e, 0x61, 0x01]).unwrap();
+    for key in 0..16 {
+        let pressed = keypad.is_key_pressed(key);
+        chip8.set_key(key, pressed);
+    }
+    chip8.step().unwrap();
+    assert_eq!(chip8.pc(), 0x204);
+}
+
+#[test]
+fn pad_presses_and_releases_are_queued() {
+    let _sdl = lock_sdl();
+    let mut keypad = keypad();
+    for down in [true, false] {
+        let event = if down {
+            Event::ControllerButtonDown {
+                timestamp: 0,
+                which: PAD,
+                button: Button::A,
+            }
+        } else {
+            Event::ControllerButtonUp {
+                timestamp: 0,
+                which: PAD,
+                button: Button::A,
+            }
+        };
+        keypad.inject_event(event);
+    }
+    assert_eq!(keypad.pop_key_event(), Some(KeyEvent::Pressed(0x5)));
+    assert_eq!(keypad.pop_key_event(), Some(KeyEvent::Released(0x5)));
+    assert_eq!(keypad.pop_key_event(), None);
+}
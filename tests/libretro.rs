@@ -0,0 +1,326 @@
+// drives the libretro core like a bare frontend: runs a ROM for a few seconds holding a key and
+// checks the screen, the audio and that a save state restores the machine. once linked into the
+// test and once from the shared library a frontend would load, which needs libdl
+#![cfg(feature = "libretro")]
+
+use chip8::libretro::*;
+use std::ffi::{c_char, c_int, c_uint, c_void, CStr, CString};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Mutex, MutexGuard};
+
+const WIDTH: usize = 64;
+const HEIGHT: usize = 32;
+const FRAMES: usize = 180;
+
+#[repr(C)]
+struct Variable {
+    key: *const std::ffi::c_char,
+    value: *const std::ffi::c_char,
+}
+
+// what the callbacks saw, they can't capture anything
+struct Seen {
+    options: Vec<String>,
+    screen: Vec<bool>,
+    frames: usize,
+    samples: usize,
+}
+
+static SEEN: Mutex<Seen> = Mutex::new(Seen {
+    options: Vec::new(),
+    screen: Vec::new(),
+    frames: 0,
+    samples: 0,
+});
+
+// both tests report to the same callbacks, they take turns
+static CORE: Mutex<()> = Mutex::new(());
+
+fn take_turn() -> MutexGuard<'static, ()> {
+    let turn = CORE.lock().unwrap_or_else(|e| e.into_inner());
+    let mut seen = SEEN.lock().unwrap_or_else(|e| e.into_inner());
+    seen.options.clear();
+    seen.screen.clear();
+    seen.frames = 0;
+    seen.samples = 0;
+    turn
+}
+
+unsafe extern "C" fn environment(cmd: c_uint, data: *mut c_void) -> bool {
+    match cmd {
+        // pixel format and input descriptors
+        10 | 11 => true,
+        // core options: remember the keys and leave every option at its default
+        16 => {
+            let mut variable = data as *const Variable;
+            let mut seen = SEEN.lock().unwrap();
+            while !(*variable).key.is_null() {
+                let key = CStr::from_ptr((*variable).key).to_string_lossy();
+                seen.options.push(key.into_owned());
+                variable = variable.add(1);
+            }
+            true
+        }
+        15 => false,
+        17 => {
+            *(data as *mut bool) = false;
+            true
+        }
+        _ => false,
+    }
+}
+
+unsafe extern "C" fn video_refresh(
+    data: *const c_void,
+    width: c_uint,
+    height: c_uint,
+    pitch: usize,
+) {
+    let mut seen = SEEN.lock().unwrap();
+    seen.frames += 1;
+    if data.is_null() {
+        return;
+    }
+    seen.screen = (0..height as usize)
+        .flat_map(|y| {
+            let row = (data as *const u8).add(y * pitch) as *const u32;
+            (0..width as usize).map(move |x| *row.add(x) != 0)
+        })
+        .collect();
+}
+
+unsafe extern "C" fn audio_sample(_left: i16, _right: i16) {}
+
+unsafe extern "C" fn audio_sample_batch(_data: *const i16, frames: usize) -> usize {
+    SEEN.lock().unwrap().samples += frames;
+    frames
+}
+
+unsafe extern "C" fn input_poll() {}
+
+// hold up on the d-pad, chip8 key 2
+unsafe extern "C" fn input_state(_port: c_uint, device: c_uint, _index: c_uint, id: c_uint) -> i16 {
+    (device == 1 && id == 4) as i16
+}
+
+fn screen() -> Vec<bool> {
+    SEEN.lock().unwrap().screen.clone()
+}
+
+#[test]
+fn core_runs_a_rom_and_restores_a_state() {
+    let _turn = take_turn();
+    let rom = std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/games/PONG")).unwrap();
+    assert_eq!(retro_api_version(), 1);
+    unsafe { retro_set_environment(environment) };
+    retro_set_video_refresh(video_refresh);
+    retro_set_audio_sample(audio_sample);
+    retro_set_audio_sample_batch(audio_sample_batch);
+    retro_set_input_poll(input_poll);
+    retro_set_input_state(input_state);
+    retro_init();
+    assert!(SEEN
+        .lock()
+        .unwrap()
+        .options
+        .contains(&"chip8_quirks".to_string()));
+
+    let game = RetroGameInfo {
+        path: std::ptr::null(),
+        data: rom.as_ptr() as *const c_void,
+        size: rom.len(),
+        meta: std::ptr::null(),
+    };
+    assert!(
+        unsafe { retro_load_game(&game) },
+        "the core refused the ROM"
+    );
+    for _ in 0..FRAMES {
+        retro_run();
+    }
+    {
+        let seen = SEEN.lock().unwrap();
+        assert_eq!(seen.frames, FRAMES);
+        assert_eq!(seen.screen.len(), WIDTH * HEIGHT);
+        assert!(seen.screen.contains(&true), "nothing on screen");
+        assert!(seen.samples > 0, "no audio");
+    }
+
+    let mut state = vec![0u8; retro_serialize_size()];
+    assert!(unsafe { retro_serialize(state.as_mut_ptr() as *mut c_void, state.len()) });
+    retro_run();
+    let saved = screen();
+    for _ in 0..30 {
+        retro_run();
+    }
+    assert!(unsafe { retro_unserialize(state.as_ptr() as *const c_void, state.len()) });
+    retro_run();
+    assert_eq!(screen(), saved, "the save state didn't restore the screen");
+
+    retro_unload_game();
+    retro_deinit();
+}
+
+#[link(name = "dl")]
+extern "C" {
+    fn dlopen(filename: *const c_char, flags: c_int) -> *mut c_void;
+    fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+    fn dlerror() -> *const c_char;
+}
+const RTLD_NOW: c_int = 2;
+
+// what a frontend passes to retro_load_game, declared here the way a C frontend would
+#[repr(C)]
+struct GameInfo {
+    path: *const c_char,
+    data: *const c_void,
+    size: usize,
+    meta: *const c_char,
+}
+
+// every function the libretro API has a frontend look up
+const API: [&str; 25] = [
+    "retro_api_version",
+    "retro_init",
+    "retro_deinit",
+    "retro_set_environment",
+    "retro_set_video_refresh",
+    "retro_set_audio_sample",
+    "retro_set_audio_sample_batch",
+    "retro_set_input_poll",
+    "retro_set_input_state",
+    "retro_set_controller_port_device",
+    "retro_get_system_info",
+    "retro_get_system_av_info",
+    "retro_load_game",
+    "retro_load_game_special",
+    "retro_unload_game",
+    "retro_get_region",
+    "retro_reset",
+    "retro_run",
+    "retro_serialize_size",
+    "retro_serialize",
+    "retro_unserialize",
+    "retro_cheat_reset",
+    "retro_cheat_set",
+    "retro_get_memory_data",
+    "retro_get_memory_size",
+];
+
+// cargo test only builds the rlib, so build the shared library the way the readme does, in its
+// own target directory like tests/c_api.rs
+fn build_core(root: &Path, target_dir: &Path) -> PathBuf {
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let status = Command::new(cargo)
+        .args([
+            "rustc",
+            "--lib",
+            "--no-default-features",
+            "--features",
+            "libretro",
+        ])
+        .args(["--crate-type", "cdylib"])
+        .current_dir(root)
+        .env("CARGO_TARGET_DIR", target_dir)
+        .status()
+        .expect("can't run cargo");
+    assert!(status.success(), "the core doesn't build");
+    target_dir.join(format!(
+        "debug/{}chip8{}",
+        std::env::consts::DLL_PREFIX,
+        std::env::consts::DLL_SUFFIX
+    ))
+}
+
+struct Core {
+    handle: *mut c_void,
+}
+
+impl Core {
+    fn open(path: &Path) -> Self {
+        let name = CString::new(path.to_str().unwrap()).unwrap();
+        let handle = unsafe { dlopen(name.as_ptr(), RTLD_NOW) };
+        if handle.is_null() {
+            let error = unsafe { CStr::from_ptr(dlerror()) };
+            panic!("can't load {}: {}", path.display(), error.to_string_lossy());
+        }
+        Self { handle }
+    }
+
+    fn exports(&self, name: &str) -> bool {
+        let symbol = CString::new(name).unwrap();
+        !unsafe { dlsym(self.handle, symbol.as_ptr()) }.is_null()
+    }
+
+    // look up a retro_* function. T has to be the matching fn pointer type
+    unsafe fn get<T: Copy>(&self, name: &str) -> T {
+        let symbol = CString::new(name).unwrap();
+        let address = dlsym(self.handle, symbol.as_ptr());
+        assert!(!address.is_null(), "the core doesn't export {}", name);
+        std::mem::transmute_copy(&address)
+    }
+}
+
+#[test]
+fn shared_library_loads_in_a_frontend() {
+    let _turn = take_turn();
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let target_dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("libretro");
+    let core = Core::open(&build_core(&root, &target_dir));
+    let missing: Vec<_> = API.iter().filter(|name| !core.exports(name)).collect();
+    assert!(missing.is_empty(), "not exported: {:?}", missing);
+    let rom = std::fs::read(root.join("games/PONG")).unwrap();
+
+    unsafe {
+        let api_version: extern "C" fn() -> c_uint = core.get("retro_api_version");
+        assert_eq!(api_version(), 1);
+        let set_environment: extern "C" fn(unsafe extern "C" fn(c_uint, *mut c_void) -> bool) =
+            core.get("retro_set_environment");
+        let set_video_refresh: extern "C" fn(
+            unsafe extern "C" fn(*const c_void, c_uint, c_uint, usize),
+        ) = core.get("retro_set_video_refresh");
+        let set_audio_sample: extern "C" fn(unsafe extern "C" fn(i16, i16)) =
+            core.get("retro_set_audio_sample");
+        let set_audio_sample_batch: extern "C" fn(
+            unsafe extern "C" fn(*const i16, usize) -> usize,
+        ) = core.get("retro_set_audio_sample_batch");
+        let set_input_poll: extern "C" fn(unsafe extern "C" fn()) =
+            core.get("retro_set_input_poll");
+        let set_input_state: extern "C" fn(
+            unsafe extern "C" fn(c_uint, c_uint, c_uint, c_uint) -> i16,
+        ) = core.get("retro_set_input_state");
+        set_environment(environment);
+        set_video_refresh(video_refresh);
+        set_audio_sample(audio_sample);
+        set_audio_sample_batch(audio_sample_batch);
+        set_input_poll(input_poll);
+        set_input_state(input_state);
+
+        let init: extern "C" fn() = core.get("retro_init");
+        init();
+        let game = GameInfo {
+            path: std::ptr::null(),
+            data: rom.as_ptr() as *const c_void,
+            size: rom.len(),
+            meta: std::ptr::null(),
+        };
+        let load_game: extern "C" fn(*const GameInfo) -> bool = core.get("retro_load_game");
+        assert!(load_game(&game), "the core refused the ROM");
+        let run: extern "C" fn() = core.get("retro_run");
+        for _ in 0..FRAMES {
+            run();
+        }
+        let unload_game: extern "C" fn() = core.get("retro_unload_game");
+        let deinit: extern "C" fn() = core.get("retro_deinit");
+        unload_game();
+        deinit();
+    }
+
+    let seen = SEEN.lock().unwrap();
+    assert!(seen.options.contains(&"chip8_quirks".to_string()));
+    assert_eq!(seen.frames, FRAMES);
+    assert_eq!(seen.screen.len(), WIDTH * HEIGHT);
+    assert!(seen.screen.contains(&true), "nothing on screen");
+    assert!(seen.samples > 0, "no audio");
+}
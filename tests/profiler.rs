@@ -0,0 +1,57 @@
+// profiles small programs and checks the call graph and per routine counts
+use chip8::profiler::RoutineStats;
+use chip8::Chip8;
+
+fn profile(program: &[u8], steps: usize, setup: impl FnOnce(&mut Chip8)) -> Chip8 {
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(program).unwrap();
+    setup(&mut chip8);
+    chip8.enable_profiler();
+    for _ in 0..steps {
+        chip8.step().unwrap();
+    }
+    chip8
+}
+
+fn routine(chip8: &Chip8, address: u16) -> Option<RoutineStats> {
+    chip8
+        .profiler()
+        .unwrap()
+        .routines()
+        .into_iter()
+        .find(|(routine, _)| *routine == address)
+        .map(|(_, stats)| stats)
+}
+
+#[test]
+fn faulting_calls_are_not_profiled() {
+    // 200: CALL 204; 202: JP 202; 204: CALL 208 overflows a stack of one; 206: RET
+    let program = [0x22, 0x04, 0x12, 0x02, 0x22, 0x08, 0x00, 0xee];
+    let chip8 = profile(&program, 6, |chip8| chip8.set_stack_depth(1));
+
+    let profiler = chip8.profiler().unwrap();
+    assert_eq!(profiler.edges(), vec![((0x200, 0x204), 1)]);
+    assert!(routine(&chip8, 0x208).is_none());
+    assert_eq!(profiler.count_at(0x204), 0);
+    // the RET after the skipped call still returns from 204
+    assert_eq!(routine(&chip8, 0x204).unwrap().inclusive, 1);
+}
+
+#[test]
+fn recursion_is_counted_once() {
+    // 200: LD V0, 3; CALL 206; JP 204
+    // 206: SE V0, 0; JP 20C; RET
+    // 20C: ADD V0, -1; CALL 206; RET
+    let program = [
+        0x60, 0x03, 0x22, 0x06, 0x12, 0x04, 0x30, 0x00, 0x12, 0x0c, 0x00, 0xee, 0x70, 0xff, 0x22,
+        0x06, 0x00, 0xee,
+    ];
+    let chip8 = profile(&program, 40, |_| {});
+
+    let stats = routine(&chip8, 0x206).unwrap();
+    assert_eq!(stats.calls, 4);
+    // 206 only ever calls itself, so everything inside it is its own work
+    assert_eq!(stats.inclusive, stats.exclusive);
+    let total = chip8.profiler().unwrap().total();
+    assert_eq!(routine(&chip8, 0x200).unwrap().inclusive, total);
+}
@@ -0,0 +1,90 @@
+// narrows a RAM search down to the bytes a test pokes, the way a player would find a score
+use chip8::ramsearch::{Candidate, RamSearch, Relation, Watchpoint};
+use chip8::Chip8;
+
+fn addresses(search: &RamSearch, chip8: &Chip8) -> Vec<u16> {
+    search
+        .candidates(chip8)
+        .iter()
+        .map(|candidate| candidate.address)
+        .collect()
+}
+
+#[test]
+fn passes_narrow_the_candidates() {
+    let mut chip8 = Chip8::new();
+    chip8.memory_mut()[0x300..0x303].copy_from_slice(&[5, 5, 5]);
+    let mut search = RamSearch::new(&chip8);
+    assert_eq!(search.len(), 0x1000);
+
+    let memory = chip8.memory_mut();
+    memory[0x300] += 1;
+    memory[0x301] += 1;
+    memory[0x302] += 2;
+    assert_eq!(search.filter(&chip8, Relation::Increased), 3);
+    assert_eq!(addresses(&search, &chip8), [0x300, 0x301, 0x302]);
+
+    // each pass compares with the snapshot of the one before
+    let memory = chip8.memory_mut();
+    memory[0x300] -= 1;
+    memory[0x301] += 1;
+    memory[0x302] -= 1;
+    assert_eq!(search.filter(&chip8, Relation::Decreased), 2);
+    assert_eq!(addresses(&search, &chip8), [0x300, 0x302]);
+
+    chip8.memory_mut()[0x300] = 9;
+    assert_eq!(
+        search.candidates(&chip8),
+        [
+            Candidate {
+                address: 0x300,
+                previous: 5,
+                value: 9
+            },
+            Candidate {
+                address: 0x302,
+                previous: 6,
+                value: 6
+            },
+        ]
+    );
+    assert_eq!(search.filter(&chip8, Relation::EqualTo(9)), 1);
+    assert_eq!(addresses(&search, &chip8), [0x300]);
+    assert_eq!(search.passes(), 3);
+
+    chip8.memory_mut()[0x300] = 0;
+    assert_eq!(search.filter(&chip8, Relation::Changed), 1);
+    chip8.memory_mut()[0x300] = 1;
+    assert_eq!(search.filter(&chip8, Relation::Equal), 0);
+    assert!(search.is_empty());
+}
+
+#[test]
+fn equal_drops_the_poked_byte() {
+    let mut chip8 = Chip8::new();
+    let mut search = RamSearch::new(&chip8);
+    chip8.memory_mut()[0x345] = 1;
+    assert_eq!(search.filter(&chip8, Relation::Equal), 0xfff);
+    assert!(!addresses(&search, &chip8).contains(&0x345));
+    // the byte stays the same from here on, but it is gone for good
+    assert_eq!(search.filter(&chip8, Relation::Equal), 0xfff);
+}
+
+#[test]
+fn watchpoints_report_each_change_once() {
+    let mut chip8 = Chip8::new();
+    chip8.memory_mut()[0x300] = 3;
+    let mut watchpoint = Watchpoint::new(&chip8, 0x300);
+    assert_eq!(watchpoint.check(&chip8), None);
+
+    chip8.memory_mut()[0x300] = 7;
+    chip8.memory_mut()[0x301] = 1;
+    assert_eq!(watchpoint.check(&chip8), Some((3, 7)));
+    assert_eq!(watchpoint.check(&chip8), None);
+    assert_eq!(watchpoint.value(), 7);
+
+    // a change and a change back between checks goes unseen
+    chip8.memory_mut()[0x300] = 8;
+    chip8.memory_mut()[0x300] = 7;
+    assert_eq!(watchpoint.check(&chip8), None);
+}
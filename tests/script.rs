@@ -0,0 +1,131 @@
+// runs small programs under rhai scripts and checks the hooks see the machine at the right moments
+#![cfg(feature = "scripting")]
+
+use chip8::script::Script;
+use chip8::Chip8;
+
+fn machine(rom: &[u8]) -> Chip8 {
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(rom).unwrap();
+    chip8
+}
+
+// 200: LD V0, 00; 202: ADD V0, 01; 204: JP 202
+const COUNTER: [u8; 6] = [0x60, 0x00, 0x70, 0x01, 0x12, 0x02];
+
+#[test]
+fn pc_hooks_run_before_the_instruction() {
+    let mut chip8 = machine(&COUNTER);
+    let mut script = Script::new(
+        r#"
+        let hits = 0;
+        on_pc(0x202, |pc| {
+            hits += 1;
+            draw_text(0, `${hits} ${pc} ${v(0)}`);
+        });
+        "#,
+    )
+    .unwrap();
+    script.run_frame(&mut chip8).unwrap();
+    // every ADD ran after its hook, which saw V0 before it
+    let adds = chip8.v()[0];
+    assert!(adds > 1);
+    assert_eq!(script.overlay(), [format!("{} 514 {}", adds, adds - 1)]);
+    assert_eq!(script.error(), None);
+}
+
+#[test]
+fn write_hooks_see_the_stored_value() {
+    // 200: LD V0, 7B; 202: LD I, 300; 204: LD B, V0; 206: JP 206
+    let mut chip8 = machine(&[0x60, 0x7b, 0xa3, 0x00, 0xf0, 0x33, 0x12, 0x06]);
+    let mut script = Script::new(
+        r#"
+        on_write(0x302, |address, value| draw_text(0, `${address} ${value} ${peek(0x301)}`));
+        on_write(0x303, |address, value| draw_text(1, "past the digits"));
+        "#,
+    )
+    .unwrap();
+    script.run_frame(&mut chip8).unwrap();
+    script.run_frame(&mut chip8).unwrap();
+    assert_eq!(script.overlay(), ["770 3 2"]);
+}
+
+#[test]
+fn sound_hooks_run_when_the_buzzer_starts_and_stops() {
+    // 200: LD V0, 02; 202: LD ST, V0; 204: JP 204
+    let mut chip8 = machine(&[0x60, 0x02, 0xf0, 0x18, 0x12, 0x04]);
+    let mut script = Script::new(
+        r#"
+        let changes = "";
+        on_sound(|on| {
+            changes += `${on} on frame ${frame()}, `;
+            draw_text(0, changes);
+        });
+        "#,
+    )
+    .unwrap();
+    for _ in 0..5 {
+        script.run_frame(&mut chip8).unwrap();
+    }
+    assert_eq!(script.overlay(), ["true on frame 0, false on frame 2, "]);
+}
+
+#[test]
+fn keys_are_held_until_released() {
+    let mut chip8 = machine(&COUNTER);
+    let mut script = Script::new(
+        r#"
+        press(0xa);
+        on_frame(|| {
+            if frame() == 1 {
+                press(5);
+            } else {
+                release(5);
+                release(0xa);
+            }
+        });
+        "#,
+    )
+    .unwrap();
+    script.run_frame(&mut chip8).unwrap();
+    assert!(script.is_key_pressed(0xa));
+    assert!(script.is_key_pressed(5));
+    script.run_frame(&mut chip8).unwrap();
+    assert!(!(0..16).any(|key| script.is_key_pressed(key)));
+}
+
+#[test]
+fn a_failing_script_leaves_the_machine_running() {
+    let mut chip8 = machine(&COUNTER);
+    let mut script = Script::new(
+        r#"
+        let hits = 0;
+        on_pc(0x202, |pc| {
+            hits += 1;
+            if hits == 3 {
+                poke(0x1000, 1);
+            }
+        });
+        on_frame(|| draw_text(0, "still running"));
+        "#,
+    )
+    .unwrap();
+    script.run_frame(&mut chip8).unwrap();
+    let error = script.error().expect("the script didn't fail");
+    assert!(error.contains("outside of memory"), "{}", error);
+    // the interpreter moved back out of the script with the whole frame run, the frame hook
+    // never ran
+    assert_eq!(chip8.memory()[0x200..0x206], COUNTER);
+    let adds = chip8.v()[0];
+    assert_eq!(adds as u32, chip8.cycles_per_frame() / 2);
+    assert!(script.overlay().is_empty());
+
+    script.run_frame(&mut chip8).unwrap();
+    assert!(chip8.v()[0] > adds);
+    assert!(script.overlay().is_empty());
+}
+
+#[test]
+fn syntax_errors_are_reported_on_load() {
+    assert!(Script::new("let x = ;").is_err());
+}
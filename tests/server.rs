@@ -0,0 +1,235 @@
+// talks JSON-RPC to a control server on a local port and checks the replies, while the test plays
+// the host loop polling the server
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+
+use chip8::server::Server;
+use chip8::{Chip8, KeyEvent};
+
+struct Host {
+    server: Server,
+    chip8: Chip8,
+    paused: bool,
+    writer: TcpStream,
+    reader: BufReader<TcpStream>,
+}
+
+impl Host {
+    // 200: LD V0, 11; 202: ADD V0, 01; 204: JP 204
+    fn new() -> Self {
+        let server = Server::bind("127.0.0.1:0").unwrap();
+        let mut chip8 = Chip8::new();
+        chip8
+            .load_rom(&[0x60, 0x11, 0x70, 0x01, 0x12, 0x04])
+            .unwrap();
+        let writer = TcpStream::connect(server.address()).unwrap();
+        writer
+            .set_read_timeout(Some(Duration::from_millis(10)))
+            .unwrap();
+        let reader = BufReader::new(writer.try_clone().unwrap());
+        Self {
+            server,
+            chip8,
+            paused: true,
+            writer,
+            reader,
+        }
+    }
+
+    // send a line and poll the server until the reply comes back
+    fn request(&mut self, line: &str) -> String {
+        writeln!(self.writer, "{}", line).unwrap();
+        self.next_line()
+    }
+
+    // poll the server until it sends the client a line
+    fn next_line(&mut self) -> String {
+        let mut reply = String::new();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            self.server.poll(&mut self.chip8, &mut self.paused);
+            match self.reader.read_line(&mut reply) {
+                Ok(_) if reply.ends_with('\n') => return reply.trim_end().to_string(),
+                Ok(_) => {}
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+                Err(e) => panic!("{}", e),
+            }
+        }
+        panic!("nothing came back, got {:?} so far", reply);
+    }
+
+    fn call(&mut self, method: &str, params: &str) -> String {
+        self.request(&format!(
+            r#"{{"jsonrpc":"2.0","method":"{}","params":{},"id":1}}"#,
+            method, params
+        ))
+    }
+}
+
+fn is_error(reply: &str, code: i64) -> bool {
+    reply.contains(&format!(r#""error":{{"code":{},"#, code))
+}
+
+#[test]
+fn step_runs_instructions() {
+    let mut host = Host::new();
+    let reply = host.call("step", r#"{"count":2}"#);
+    assert!(
+        reply.starts_with(r#"{"jsonrpc":"2.0","result":{"pc":516,"#),
+        "{}",
+        reply
+    );
+    assert!(reply.contains(r#""v":[18,0,"#), "{}", reply);
+    assert!(reply.ends_with(r#""id":1}"#), "{}", reply);
+    assert_eq!(host.chip8.pc(), 0x204);
+}
+
+#[test]
+fn memory_is_read_and_written() {
+    let mut host = Host::new();
+    let reply = host.call("write_memory", r#"{"address":768,"data":"qrs="}"#);
+    assert_eq!(reply, r#"{"jsonrpc":"2.0","result":null,"id":1}"#);
+    assert_eq!(host.chip8.memory()[0x300..0x302], [0xaa, 0xbb]);
+
+    let reply = host.call("read_memory", r#"{"address":768,"length":2}"#);
+    assert!(
+        reply.contains(r#""result":{"address":768,"data":"qrs="}"#),
+        "{}",
+        reply
+    );
+
+    let reply = host.call("read_memory", r#"{"address":4095,"length":2}"#);
+    assert!(is_error(&reply, -32602), "{}", reply);
+    let reply = host.call("write_memory", r#"{"address":4095,"data":"qrs="}"#);
+    assert!(is_error(&reply, -32602), "{}", reply);
+    assert_eq!(host.chip8.memory()[0xfff], 0);
+}
+
+#[test]
+fn roms_are_sent_not_read_from_the_host() {
+    let mut host = Host::new();
+    let reply = host.call("load_rom", r#"{"path":"/etc/passwd"}"#);
+    assert!(is_error(&reply, -32602), "{}", reply);
+    assert_eq!(host.chip8.memory()[0x200], 0x60);
+
+    // 200: JP 200
+    let reply = host.call("load_rom", r#"{"data":"EgA="}"#);
+    assert_eq!(reply, r#"{"jsonrpc":"2.0","result":null,"id":1}"#);
+    assert_eq!(host.chip8.memory()[0x200..0x202], [0x12, 0x00]);
+}
+
+#[test]
+fn only_loopback_addresses_are_bound_by_default() {
+    let error = Server::bind("0.0.0.0:0")
+        .err()
+        .expect("bound a remote address");
+    assert_eq!(error.kind(), ErrorKind::PermissionDenied);
+    assert!(Server::bind("localhost:0").is_ok());
+    assert!(Server::bind_remote("0.0.0.0:0").is_ok());
+}
+
+#[test]
+fn malformed_base64_is_refused() {
+    let mut host = Host::new();
+    for data in ["qrs", "qr=s", "q===", "qrt=", "qr!="] {
+        let reply = host.call(
+            "write_memory",
+            &format!(r#"{{"address":768,"data":"{}"}}"#, data),
+        );
+        assert!(is_error(&reply, -32602), "{}: {}", data, reply);
+    }
+    assert_eq!(host.chip8.memory()[0x300], 0);
+}
+
+#[test]
+fn long_lines_are_an_invalid_request() {
+    let mut host = Host::new();
+    let data = "A".repeat(100_000);
+    let reply = host.call(
+        "write_memory",
+        &format!(r#"{{"address":768,"data":"{}"}}"#, data),
+    );
+    assert!(is_error(&reply, -32600), "{}", reply);
+    assert!(reply.ends_with(r#""id":null}"#), "{}", reply);
+    // the rest of the line is skipped and the next request is answered
+    let reply = host.call("get_registers", "null");
+    assert!(reply.contains(r#""pc":512,"#), "{}", reply);
+}
+
+#[test]
+fn registers_are_set_all_or_nothing() {
+    let mut host = Host::new();
+    let reply = host.call("set_registers", r#"{"pc":768,"V3":7,"dt":9}"#);
+    assert!(reply.contains(r#""pc":768,"#), "{}", reply);
+    assert_eq!(host.chip8.v()[3], 7);
+    assert_eq!(host.chip8.delay_timer(), 9);
+
+    let reply = host.call("set_registers", r#"{"v3":8,"v4":256}"#);
+    assert!(is_error(&reply, -32602), "{}", reply);
+    assert_eq!(host.chip8.v()[3], 7);
+    let reply = host.call("set_registers", r#"{"vg":1}"#);
+    assert!(is_error(&reply, -32602), "{}", reply);
+}
+
+#[test]
+fn addresses_stay_inside_memory() {
+    let mut host = Host::new();
+    let reply = host.call("set_registers", r#"{"pc":4095,"i":4095}"#);
+    assert!(reply.contains(r#""pc":4095,"i":4095,"#), "{}", reply);
+
+    for registers in [r#"{"pc":4096}"#, r#"{"i":4096}"#, r#"{"i":65535}"#] {
+        let reply = host.call("set_registers", registers);
+        assert!(is_error(&reply, -32602), "{}", reply);
+    }
+    assert_eq!(host.chip8.pc(), 0xfff);
+    assert_eq!(host.chip8.index(), 0xfff);
+}
+
+#[test]
+fn malformed_json_is_a_parse_error() {
+    let mut host = Host::new();
+    let reply = host.request(r#"{"jsonrpc":"2.0","method":"#);
+    assert!(is_error(&reply, -32700), "{}", reply);
+    assert!(reply.ends_with(r#""id":null}"#), "{}", reply);
+
+    // a request nested deep enough to overflow a recursive parser
+    let deep = format!("{}{}", "[".repeat(10_000), "]".repeat(10_000));
+    let reply = host.request(&deep);
+    assert!(is_error(&reply, -32700), "{}", reply);
+    assert!(reply.contains("nested too deep"), "{}", reply);
+    // 64 levels with the request object itself are fine
+    let nested = format!("{}{}", "[".repeat(63), "]".repeat(63));
+    let reply = host.request(&format!(
+        r#"{{"jsonrpc":"2.0","method":"status","params":{},"id":5}}"#,
+        nested
+    ));
+    assert!(reply.contains(r#""result":"#), "{}", reply);
+
+    let reply = host.request(r#"{"jsonrpc":"2.0","id":4}"#);
+    assert!(is_error(&reply, -32600), "{}", reply);
+    // the connection still works afterwards
+    let reply = host.call("get_registers", "null");
+    assert!(reply.contains(r#""pc":512,"#), "{}", reply);
+}
+
+#[test]
+fn key_subscribers_hear_the_host_keys() {
+    let mut host = Host::new();
+    let reply = host.call("subscribe", r#"{"events":["key"]}"#);
+    assert!(reply.contains(r#""result":null"#), "{}", reply);
+
+    host.server.key_event(KeyEvent::Pressed(0xa));
+    host.server.key_event(KeyEvent::Released(0xa));
+    let pressed = host.next_line();
+    assert_eq!(
+        pressed,
+        r#"{"jsonrpc":"2.0","method":"key","params":{"frame":0,"key":10,"pressed":true}}"#
+    );
+    let released = host.next_line();
+    assert!(
+        released.contains(r#""key":10,"pressed":false"#),
+        "{}",
+        released
+    );
+}
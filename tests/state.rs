@@ -0,0 +1,75 @@
+// saves the machine in the middle of a program and checks a fresh one picks up where it left off
+use chip8::{Chip8, Chip8Error, FaultPolicy};
+
+fn restore(chip8: &Chip8) -> Chip8 {
+    let mut restored = Chip8::new();
+    restored.load_state(&chip8.save_state()).unwrap();
+    restored
+}
+
+#[test]
+fn halted_machines_stay_halted() {
+    // 200: an opcode no platform defines
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&[0x51, 0x21]).unwrap();
+    chip8.set_fault_policy(FaultPolicy::Halt);
+    assert!(chip8.step().is_err());
+
+    let mut restored = restore(&chip8);
+    assert!(restored.is_halted());
+    assert_eq!(
+        restored.step(),
+        Err(Chip8Error::UnknownOpcode {
+            address: 0x200,
+            opcode: 0x5121
+        })
+    );
+    assert!(!restore(&Chip8::new()).is_halted());
+}
+
+#[test]
+fn audio_pattern_and_pitch_are_kept() {
+    // 200: LD I, 208; 202: AUDIO; 204: LD V0, 50; 206: PITCH V0; 208: the pattern
+    let mut program = vec![0xa2, 0x08, 0xf0, 0x02, 0x60, 0x50, 0xf0, 0x3a];
+    program.extend(0x10..0x20);
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&program).unwrap();
+    for _ in 0..4 {
+        chip8.step().unwrap();
+    }
+
+    let restored = restore(&chip8);
+    let pattern: Vec<u8> = (0x10..0x20).collect();
+    assert_eq!(restored.audio_pattern().unwrap()[..], pattern[..]);
+    assert_eq!(restored.audio_pitch(), 0x50);
+    assert_eq!(restore(&Chip8::new()).audio_pattern(), None);
+}
+
+#[test]
+fn old_versions_are_rejected() {
+    let mut state = Chip8::new().save_state();
+    state[4] = 1;
+    assert!(Chip8::new().load_state(&state).is_err());
+}
+
+#[test]
+fn stack_pointer_fits_the_configured_depth() {
+    // 200: CALL 202; 202: CALL 204; 204: CALL 206; 206: JP 206
+    let mut chip8 = Chip8::new();
+    chip8
+        .load_rom(&[0x22, 0x02, 0x22, 0x04, 0x22, 0x06, 0x12, 0x06])
+        .unwrap();
+    for _ in 0..3 {
+        chip8.step().unwrap();
+    }
+    let state = chip8.save_state();
+
+    let mut shallow = Chip8::new();
+    shallow.set_stack_depth(2);
+    assert!(shallow.load_state(&state).is_err());
+    assert_eq!(shallow.pc(), 0x200);
+    let mut exact = Chip8::new();
+    exact.set_stack_depth(3);
+    exact.load_state(&state).unwrap();
+    assert_eq!(exact.pc(), 0x206);
+}
@@ -0,0 +1,75 @@
+// renders the buzzer a frame at a time while a program runs, the way the headless audio backend
+// does, and compares the samples against the waveform they should have
+use chip8::{Buzzer, Chip8, Tone, SAMPLES_PER_FRAME, SAMPLE_RATE};
+
+const PATTERN: [u8; 16] = [
+    0xf0, 0x0f, 0xaa, 0x55, 0xff, 0x00, 0xcc, 0x33, 0x80, 0x01, 0x7e, 0x81, 0x00, 0xff, 0x0f, 0xf0,
+];
+
+// LD I, pattern; F002; LD V0, 112; LD pitch, V0; LD V1, 3; LD ST, V1; then loop forever
+fn xo_chip_beep() -> Vec<u8> {
+    let mut rom = vec![
+        0xa2, 0x10, 0xf0, 0x02, 0x60, 0x70, 0xf0, 0x3a, 0x61, 0x03, 0xf1, 0x18, 0x12, 0x0c, 0x00,
+        0x00,
+    ];
+    rom.extend_from_slice(&PATTERN);
+    rom
+}
+
+// LD V1, 3; LD ST, V1; then loop forever
+const PLAIN_BEEP: [u8; 6] = [0x61, 0x03, 0xf1, 0x18, 0x12, 0x04];
+
+fn render(chip8: &mut Chip8, buzzer: &mut Buzzer, frames: usize) -> Vec<f32> {
+    let mut samples = vec![0.0; SAMPLES_PER_FRAME * frames];
+    for frame in samples.chunks_mut(SAMPLES_PER_FRAME) {
+        chip8.run_frame().unwrap();
+        buzzer.set_pattern(chip8.audio_pattern().copied());
+        buzzer.set_pitch(chip8.audio_pitch());
+        buzzer.set_gate(chip8.sound_active());
+        buzzer.fill(frame);
+    }
+    samples
+}
+
+#[test]
+fn pattern_plays_at_the_pitch_rate() {
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&xo_chip_beep()).unwrap();
+    let mut buzzer = Buzzer::new(Tone::default(), SAMPLE_RATE);
+    let samples = render(&mut chip8, &mut buzzer, 5);
+
+    // pitch 112 plays 4000 * 2 ^ ((112 - 64) / 48) = 8000 bits per second, one bit of the
+    // pattern after the other, for the 3 frames the sound timer runs
+    let volume = Tone::default().volume;
+    let step = 8000.0 / SAMPLE_RATE as f32;
+    let mut position = 0.0f32;
+    let mut expected = vec![0.0; samples.len()];
+    for sample in expected.iter_mut().take(SAMPLES_PER_FRAME * 3) {
+        let bit = position as usize;
+        let set = PATTERN[bit / 8] & (0x80 >> (bit % 8)) != 0;
+        *sample = if set { volume } else { -volume };
+        position = (position + step) % 128.0;
+    }
+    assert_eq!(samples, expected);
+}
+
+#[test]
+fn tone_comes_back_after_a_reset() {
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&xo_chip_beep()).unwrap();
+    let mut buzzer = Buzzer::new(Tone::default(), SAMPLE_RATE);
+    render(&mut chip8, &mut buzzer, 5);
+
+    // a plain CHIP-8 program afterwards sounds like it does on a fresh buzzer
+    chip8.load_rom(&PLAIN_BEEP).unwrap();
+    let samples = render(&mut chip8, &mut buzzer, 5);
+    let mut fresh = Chip8::new();
+    fresh.load_rom(&PLAIN_BEEP).unwrap();
+    let expected = render(
+        &mut fresh,
+        &mut Buzzer::new(Tone::default(), SAMPLE_RATE),
+        5,
+    );
+    assert_eq!(samples, expected);
+    assert!(samples.iter().any(|sample| *sample != 0.0));
+}